@@ -0,0 +1,12 @@
+use std::time::Instant;
+
+use sudoku_solver::Sudoku;
+
+fn main() {
+    let nearly_empty: Sudoku = vec![(0, 0, 1), (4, 4, 5), (8, 8, 9)].into_iter().collect();
+    for cap in [1, 10, 100, 1_000, 10_000] {
+        let start = Instant::now();
+        let count = nearly_empty.solution_count(cap);
+        println!("nearly empty grid, cap {:>6}: {:>6} solutions in {:?}", cap, count, start.elapsed());
+    }
+}
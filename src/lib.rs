@@ -1,4 +1,4 @@
-use std::{str::FromStr, fmt::Display, num::TryFromIntError, cmp::max};
+use std::{str::FromStr, fmt::Display, num::TryFromIntError, io::{self, BufRead, BufReader, Write}, collections::HashMap, fs::File, path::Path, ops::RangeInclusive};
 
 /// Error thrown on invalid sudoku parsing
 #[derive(Debug)]
@@ -14,164 +14,3557 @@ impl From<TryFromIntError> for SudokuError {
     }
 }
 
+impl From<io::Error> for SudokuError {
+    fn from(value: io::Error) -> Self {
+        Self {
+            details: value.to_string(),
+        }
+    }
+}
+
 /// Sudoku structure holding the 81 values of the sudoku puzzle
+#[derive(Debug, Clone, Default)]
 pub struct Sudoku {
     table: [[u8; 9]; 9],
+    /// cells filled in the original puzzle, which can't be changed by moves
+    givens: [[bool; 9]; 9],
+    /// moves applied through `set`, most recent last
+    history: Vec<Move>,
+    /// moves reverted by `undo`, available to `redo`
+    undone: Vec<Move>,
+    /// parity constraints of the odd/even variant
+    parity: [[Option<Parity>; 9]; 9],
+    /// user supplied units, checked alongside the rows and columns
+    custom_units: Vec<[(usize, usize); 9]>,
+    /// whether the custom units take the place of the 3x3 blocks
+    blocks_replaced: bool,
+}
+
+/// Grids are equal when they hold the same values. Only the values are compared: clues, move
+/// history, parity constraints and custom units or regions are ignored.
+impl PartialEq for Sudoku {
+    fn eq(&self, other: &Self) -> bool {
+        self.table == other.table
+    }
+}
+
+impl Eq for Sudoku {}
+
+/// Parity a cell must have in the odd/even sudoku variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    Even,
+    Odd,
+}
+
+impl Parity {
+    /// Bitset of the digits with this parity
+    fn mask(self) -> u16 {
+        match self {
+            Parity::Even => 0b01_0101_0100,
+            Parity::Odd => 0b10_1010_1010,
+        }
+    }
+}
+
+/// A single recorded change of a cell value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+    row: usize,
+    col: usize,
+    old: u8,
+    new: u8,
+}
+
+/// Solving algorithm selectable through [`Sudoku::solve_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Plain depth-first search over the empty cells in row-major order
+    Backtrack,
+    /// Naked and hidden singles propagation, guessing on the most constrained cell when stuck
+    ConstraintPropagation,
+    /// Logical techniques only, fails when a guess would be required
+    HumanTechniquesOnly,
+}
+
+/// Result of [`Sudoku::solve_with_budget`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveOutcome {
+    /// The grid now holds a solution
+    Solved,
+    /// The puzzle has no solution, the grid is left untouched
+    Unsolvable,
+    /// Solving needed more guesses than allowed, the grid keeps every value deduced
+    /// by propagation before the first guess
+    BudgetExceeded,
+    /// Solving needed more nested guesses than allowed, the grid is left as for `BudgetExceeded`
+    DepthExceeded,
+}
+
+/// Number of guesses a search may still make, and how deeply they may nest
+struct Budget {
+    left: usize,
+    exceeded: bool,
+    depth_left: usize,
+    too_deep: bool,
+}
+
+impl Budget {
+    fn new(left: usize) -> Self {
+        Budget { left, exceeded: false, depth_left: usize::MAX, too_deep: false }
+    }
+
+    fn with_max_depth(mut self, depth: usize) -> Self {
+        self.depth_left = depth;
+        self
+    }
+
+    /// Whether either limit was hit and the search has to unwind
+    fn stopped(&self) -> bool {
+        self.exceeded || self.too_deep
+    }
+
+    fn unlimited() -> Self {
+        Budget::new(usize::MAX)
+    }
+
+    /// Takes one guess from the budget, returning false once it is used up
+    fn spend(&mut self) -> bool {
+        if self.left == 0 {
+            self.exceeded = true;
+            return false;
+        }
+        self.left -= 1;
+        true
+    }
+}
+
+/// A group of nine cells which must hold every digit exactly once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// Row from 0 (top) to 8
+    Row(usize),
+    /// Column from 0 (left) to 8
+    Column(usize),
+    /// 3x3 block from 0 (top left) to 8 (bottom right), in row-major order
+    Block(usize),
+    /// User supplied unit, indexed in the order given to [`Sudoku::with_custom_units`]
+    Custom(usize),
+}
+
+impl Unit {
+    /// Coordinates of the nine cells of a standard unit. Custom units are only known to their
+    /// grid, callers go through `Sudoku::unit_cells` for those.
+    fn cells(self) -> [(usize, usize); 9] {
+        let mut cells = [(0, 0); 9];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            *cell = match self {
+                Unit::Row(row) => (row, i),
+                Unit::Column(col) => (i, col),
+                Unit::Block(block) => ((block / 3) * 3 + i / 3, (block % 3) * 3 + i % 3),
+                Unit::Custom(_) => panic!("the cells of a custom unit are stored in its grid"),
+            };
+        }
+        cells
+    }
+}
+
+/// Counts from 1 like the `R1C1` cell names, so `Unit::Row(0)` is `row 1`
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unit::Row(i) => write!(f, "row {}", i + 1),
+            Unit::Column(i) => write!(f, "column {}", i + 1),
+            Unit::Block(i) => write!(f, "block {}", i + 1),
+            Unit::Custom(i) => write!(f, "region {}", i + 1),
+        }
+    }
+}
+
+/// Logical solving techniques, ordered from the simplest to the hardest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Technique {
+    /// A cell with a single remaining candidate
+    NakedSingle,
+    /// A digit with a single possible cell inside a unit
+    HiddenSingle,
+    /// The candidates of a digit in a block all lie on one row or column
+    PointingPair,
+    /// The candidates of a digit in a row or column all lie in one block
+    BoxLineReduction,
+    /// Two cells of a unit sharing the same two candidates
+    NakedPair,
+}
+
+/// Puzzle difficulty, as rated by [`Sudoku::rate`], ordered from the easiest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    /// Solvable with naked and hidden singles
+    Easy,
+    /// Also needs pointing pairs or box/line reductions
+    Medium,
+    /// Also needs naked pairs
+    Hard,
+    /// Can't be solved without guessing
+    Expert,
+}
+
+/// Clue counts [`Sudoku::generate`] aims for with [`Difficulty::Easy`]
+pub const EASY_CLUES: RangeInclusive<usize> = 36..=45;
+/// Clue counts [`Sudoku::generate`] aims for with [`Difficulty::Medium`]
+pub const MEDIUM_CLUES: RangeInclusive<usize> = 30..=35;
+/// Clue counts [`Sudoku::generate`] aims for with [`Difficulty::Hard`]
+pub const HARD_CLUES: RangeInclusive<usize> = 25..=29;
+/// Clue counts [`Sudoku::generate`] aims for with [`Difficulty::Expert`]
+pub const EXPERT_CLUES: RangeInclusive<usize> = 17..=24;
+
+/// Puzzle produced by [`Sudoku::generate`]
+#[derive(Debug, Clone)]
+pub struct Generated {
+    /// The generated puzzle
+    pub puzzle: Sudoku,
+    /// Difficulty the puzzle actually rates at
+    pub rating: Difficulty,
+    /// Number of candidate puzzles generated
+    pub attempts: usize,
+}
+
+impl Display for Technique {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Technique::NakedSingle => "naked single",
+            Technique::HiddenSingle => "hidden single",
+            Technique::PointingPair => "pointing pair",
+            Technique::BoxLineReduction => "box/line reduction",
+            Technique::NakedPair => "naked pair",
+        })
+    }
+}
+
+/// Outcome of one application of a logical technique
+#[derive(Debug, Clone, Copy)]
+struct Deduction {
+    technique: Technique,
+    /// unit the technique worked in, `None` for naked singles
+    unit: Option<Unit>,
+    /// value placed, `None` for techniques which only eliminate candidates
+    placed: Option<(usize, usize, u8)>,
+}
+
+/// Describes a placement as `R1C3 = 4 (hidden single in row 1)` and an elimination as
+/// `pointing pair in block 1`
+impl Display for Deduction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.placed, self.unit) {
+            (Some((row, col, value)), Some(unit)) => write!(f, "{} = {} ({} in {})", cell_name(row, col), value, self.technique, unit),
+            (Some((row, col, value)), None) => write!(f, "{} = {} ({})", cell_name(row, col), value, self.technique),
+            (None, Some(unit)) => write!(f, "{} in {}", self.technique, unit),
+            (None, None) => write!(f, "{}", self.technique),
+        }
+    }
+}
+
+/// Deductions of `Sudoku::logical_solve` and where they left the grid
+struct LogicalSolve {
+    deductions: Vec<Deduction>,
+    grid: Sudoku,
+    /// whether the grid breaks the rules or an empty cell ran out of candidates
+    contradiction: bool,
+}
+
+/// Hint returned by [`Sudoku::next_hint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    /// Row of the cell to fill
+    pub row: usize,
+    /// Column of the cell to fill
+    pub col: usize,
+    /// Value to place
+    pub value: u8,
+    /// Technique placing the value, `None` when no logical move exists and the hint is a guess
+    pub technique: Option<Technique>,
+    /// Human readable explanation of the move
+    pub explanation: String,
+}
+
+/// A digit repeated within a unit, see [`Sudoku::validate_report`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Unit holding the digit more than once
+    pub unit: Unit,
+    /// The repeated digit
+    pub digit: u8,
+    /// Every cell of the unit holding the digit
+    pub cells: Vec<(usize, usize)>,
+}
+
+/// Everything wrong with a grid, returned by [`Sudoku::validate_report`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Repeated digits, in row, column, block then custom unit order
+    pub violations: Vec<Violation>,
+    /// Whether every cell is filled
+    pub complete: bool,
+    /// Whether the grid is complete and breaks no rule
+    pub solved: bool,
+}
+
+/// Strips a leading UTF-8 byte order mark and surrounding whitespace from parser input
+fn trim_input(s: &str) -> &str {
+    s.trim_start().trim_start_matches('\u{FEFF}').trim()
+}
+
+/// Human readable `R1C1` style name of a cell, counting from 1
+fn cell_name(row: usize, col: usize) -> String {
+    format!("R{}C{}", row + 1, col + 1)
+}
+
+/// Bitset with bits 1 to 9 set, one bit per digit
+const ALL_DIGITS: u16 = 0b11_1111_1110;
+
+/// ANSI escape sequences used by the terminal renderer
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_HIGHLIGHT: &str = "\x1b[1;30;43m";
+const ANSI_CANDIDATE: &str = "\x1b[33m";
+
+/// Small deterministic pseudo random generator (splitmix64) used by the puzzle generators
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+fn swap_cells(table: &mut [[u8; 9]; 9], (r1, c1): (usize, usize), (r2, c2): (usize, usize)) {
+    let val = table[r1][c1];
+    table[r1][c1] = table[r2][c2];
+    table[r2][c2] = val;
+}
+
+/// Swaps rows and columns of a table
+fn transposed(table: &[[u8; 9]; 9]) -> [[u8; 9]; 9] {
+    std::array::from_fn(|r| std::array::from_fn(|c| table[c][r]))
+}
+
+/// All 1296 orderings of nine lines which keep the bands (or stacks) of three together
+fn line_permutations() -> Vec<[usize; 9]> {
+    const ORDERS: [[usize; 3]; 6] = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+    let mut perms = Vec::with_capacity(1296);
+    for bands in ORDERS {
+        for first in ORDERS {
+            for second in ORDERS {
+                for third in ORDERS {
+                    let inner = [first, second, third];
+                    perms.push(std::array::from_fn(|i| bands[i / 3] * 3 + inner[i / 3][i % 3]));
+                }
+            }
+        }
+    }
+    perms
+}
+
+/// Tries to map the rows of `a` from `row` onwards onto unused rows of `b`, keeping bands
+/// together and extending the digit relabeling `map` (with its inverse `rev`) consistently
+fn match_rows(
+    a: &[[u8; 9]; 9],
+    b: &[[u8; 9]; 9],
+    row: usize,
+    bands: [Option<usize>; 3],
+    used: [bool; 9],
+    map: [u8; 10],
+    rev: [u8; 10],
+) -> bool {
+    if row == 9 {
+        return true;
+    }
+    let band = row / 3;
+    for target_band in 0..3 {
+        match bands[band] {
+            Some(assigned) if assigned != target_band => continue,
+            None if bands.contains(&Some(target_band)) => continue,
+            _ => {}
+        }
+        for target in (target_band * 3)..(target_band * 3 + 3) {
+            if used[target] {
+                continue;
+            }
+            let (mut map, mut rev) = (map, rev);
+            let fits = (0..9).all(|col| {
+                let (x, y) = (a[row][col] as usize, b[target][col] as usize);
+                if (x == 0) != (y == 0) {
+                    return false;
+                }
+                if x != 0 && map[x] == 0 && rev[y] == 0 {
+                    map[x] = y as u8;
+                    rev[y] = x as u8;
+                }
+                map[x] as usize == y
+            });
+            if !fits {
+                continue;
+            }
+            let mut bands = bands;
+            bands[band] = Some(target_band);
+            let mut used = used;
+            used[target] = true;
+            if match_rows(a, b, row + 1, bands, used, map, rev) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Iterates over the digits contained in a candidate bitset
+fn digits(mask: u16) -> impl Iterator<Item = u8> {
+    (1..=9).filter(move |d| mask & (1 << d) != 0)
 }
 
 impl Sudoku {
+    /// Creates a puzzle from its values, every filled cell becoming a given
+    fn from_table(table: [[u8; 9]; 9]) -> Self {
+        let mut givens = [[false; 9]; 9];
+        for (row, line) in table.iter().enumerate() {
+            for (col, &val) in line.iter().enumerate() {
+                givens[row][col] = val != 0;
+            }
+        }
+        Sudoku { table, givens, ..Default::default() }
+    }
+
     pub fn valid(&self) -> bool {
         // validate rows
         for row in 0..9 {
-            let mut counts = [0; 9];
+            let mut counts = [0; 10];
             for col in 0..9 {
                 let val = self.table[row][col];
-                counts[(max(val, 1) - 1) as usize] += 1;
+                counts[val as usize] += 1;
             }
-            if counts.iter().any(|&count| count != 1) {
+            if counts[1..].iter().any(|&count| count != 1) {
                 return false;
             }
         }
         // validate columns
         for col in 0..9 {
-            let mut counts = [0; 9];
+            let mut counts = [0; 10];
             for row in 0..9 {
                 let val = self.table[row][col];
-                counts[(max(val, 1) - 1) as usize] += 1;
+                counts[val as usize] += 1;
             }
-            if counts.iter().any(|&count| count != 1) {
+            if counts[1..].iter().any(|&count| count != 1) {
                 return false;
             }
         }
 
         // check 3x3 blocks
-        for block_row in 0..3 {
+        for block_row in (0..3).filter(|_| !self.blocks_replaced) {
             for block_col in 0..3 {
-                let mut counts = [0; 9];
+                let mut counts = [0; 10];
                 for row in (block_row * 3)..((block_row + 1) * 3) {
                     for col in (block_col * 3)..((block_col + 1) * 3) {
                         let val = self.table[row][col];
-                        counts[(val - 1) as usize] += 1;
+                        counts[val as usize] += 1;
                     }
                 }
-                if counts.iter().any(|&count| count != 1) {
+                if counts[1..].iter().any(|&count| count != 1) {
                     return false;
                 }
             }
         }
-        true
+        // check custom units
+        for cells in &self.custom_units {
+            let mut counts = [0; 10];
+            for &(row, col) in cells {
+                counts[self.table[row][col] as usize] += 1;
+            }
+            if counts[1..].iter().any(|&count| count != 1) {
+                return false;
+            }
+        }
+        self.parity_respected()
     }
-}
-
-impl FromStr for Sudoku {
-    type Err = SudokuError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let input = s.replace("\n", "");
-        if input.len() != 81 {
-            return Err(SudokuError{details: "Table needs to have 81 cells.".to_string()});
+    /// Adds user supplied units which must hold every digit exactly once, on top of the rows,
+    /// columns and 3x3 blocks. Validation, candidates and every solver take them into account.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a coordinate is outside the grid.
+    pub fn with_custom_units(mut self, units: Vec<[(usize, usize); 9]>) -> Self {
+        for cells in &units {
+            assert!(cells.iter().all(|&(row, col)| row < 9 && col < 9), "custom unit cell outside the grid");
         }
-        let mut table = [[0u8; 9]; 9];
-        for (i, c) in input.chars().enumerate() {
-            let row = i / 9;
-            let col = i % 9;
-            let val = match c.to_digit(10) {
-                Some(c) => c,
-                None => return Err(SudokuError{details: format!("Invalid character {} at {},{}.", c, row, col)}),
-            };
-            table[row][col] = val.try_into()?;
+        self.custom_units.extend(units);
+        self
+    }
+
+    /// Like `with_custom_units`, but the units replace the standard 3x3 blocks
+    pub fn with_custom_regions(mut self, regions: Vec<[(usize, usize); 9]>) -> Self {
+        self.blocks_replaced = true;
+        self.with_custom_units(regions)
+    }
+
+    /// The main diagonal, top left to bottom right, then the anti-diagonal, top right to
+    /// bottom left. Pass them to `with_custom_units` for X-Sudoku rules.
+    pub fn diagonals() -> [[(usize, usize); 9]; 2] {
+        [std::array::from_fn(|i| (i, i)), std::array::from_fn(|i| (i, 8 - i))]
+    }
+
+    /// Returns true if the diagonal `which` (0 main, 1 anti) holds no digit twice, empty cells
+    /// are ignored
+    ///
+    /// # Panics
+    ///
+    /// Panics if `which` is not 0 or 1.
+    pub fn diagonal_is_valid(&self, which: usize) -> bool {
+        self.no_repeats(&Sudoku::diagonals()[which])
+    }
+
+    /// Coordinates of the nine cells of any unit of this grid, including custom ones
+    ///
+    /// # Panics
+    ///
+    /// Panics if a custom unit index is out of range.
+    pub fn unit_cells(&self, unit: Unit) -> [(usize, usize); 9] {
+        match unit {
+            Unit::Custom(i) => self.custom_units[i],
+            _ => unit.cells(),
         }
-        Ok(Sudoku { table })
     }
-}
 
-impl Display for Sudoku {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (i, row) in self.table.iter().enumerate() {
-            if i % 3 == 0 && i != 0 {
-                write!(f, "------+-------+------\n")?;
+    /// Number of duplicate pairs over all units: a digit appearing `k` times in a unit adds
+    /// `k * (k - 1) / 2`. Empty cells never conflict, so a valid grid returns 0.
+    pub fn count_conflicts(&self) -> usize {
+        self.units()
+            .iter()
+            .map(|(_, cells)| {
+                let mut counts = [0usize; 10];
+                for &(row, col) in cells {
+                    counts[self.table[row][col] as usize] += 1;
+                }
+                counts[1..].iter().map(|k| k * k.saturating_sub(1) / 2).sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// Returns true if every cell is filled
+    pub fn is_complete(&self) -> bool {
+        self.table.iter().flatten().all(|&val| val != 0)
+    }
+
+    /// Returns true if the grid is complete and breaks no rule, same as `valid`
+    pub fn is_solved(&self) -> bool {
+        self.valid()
+    }
+
+    /// Collects every repeated digit of every unit along with completeness, where `valid` stops
+    /// at the first problem. Broken parity constraints only show up in `solved`.
+    pub fn validate_report(&self) -> ValidationReport {
+        let mut violations = Vec::new();
+        for (unit, cells) in self.units() {
+            for digit in 1..=9u8 {
+                let holding: Vec<(usize, usize)> =
+                    cells.iter().copied().filter(|&(row, col)| self.table[row][col] == digit).collect();
+                if holding.len() > 1 {
+                    violations.push(Violation { unit, digit, cells: holding });
+                }
             }
-            for (j, &cell) in row.iter().enumerate() {
-                if j % 3 == 0 && j != 0 {
-                    write!(f, "| ")?;
+        }
+        ValidationReport { violations, complete: self.is_complete(), solved: self.is_solved() }
+    }
+
+    /// Requires a cell to hold an even or odd digit
+    pub fn set_parity(&mut self, row: usize, col: usize, parity: Parity) {
+        self.parity[row][col] = Some(parity);
+    }
+
+    /// Parity constraint of a cell, if any
+    pub fn parity(&self, row: usize, col: usize) -> Option<Parity> {
+        self.parity[row][col]
+    }
+
+    /// Bitset of the digits a cell's own constraints allow, ignoring its peers
+    fn allowed(&self, row: usize, col: usize) -> u16 {
+        self.parity[row][col].map_or(ALL_DIGITS, Parity::mask)
+    }
+
+    /// Returns true if every filled cell satisfies its parity constraint
+    fn parity_respected(&self) -> bool {
+        (0..81).all(|i| {
+            let (row, col) = (i / 9, i % 9);
+            let val = self.table[row][col];
+            val == 0 || self.allowed(row, col) & (1 << val) != 0
+        })
+    }
+
+    /// Checks the givens for a digit repeated within a unit, naming the two conflicting cells
+    pub fn validate_givens(&self) -> Result<(), SudokuError> {
+        for (unit, cells) in self.units() {
+            let mut seen: [Option<(usize, usize)>; 10] = [None; 10];
+            for (row, col) in cells {
+                if !self.givens[row][col] {
+                    continue;
+                }
+                let val = self.table[row][col] as usize;
+                if let Some((r, c)) = seen[val] {
+                    return Err(SudokuError{details: format!("Digit {} appears twice in {} at {},{} and {},{}.", val, unit, r, c, row, col)});
                 }
-                write!(f, "{} ", cell)?;
+                seen[val] = Some((row, col));
             }
-            write!(f, "\n")?;
         }
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns true if two distinct cells share a row, column or 3x3 block
+    pub fn is_peer(a: (usize, usize), b: (usize, usize)) -> bool {
+        if a == b {
+            return false;
+        }
+        a.0 == b.0 || a.1 == b.1 || (a.0 / 3 == b.0 / 3 && a.1 / 3 == b.1 / 3)
+    }
 
-    #[test]
-    fn test_validate_sudoku() {
-        let sudoku: Sudoku = "534678912\n\
-             672195348\n\
-             198342567\n\
-             859761423\n\
-             426853791\n\
-             713924856\n\
-             961537284\n\
-             287419635\n\
-             345286179"
-            .parse()
-            .unwrap();
-        println!("{}", sudoku);
-        assert!(sudoku.valid());
+    /// The empty cells among the 20 row, column and block peers of a cell. The peers are
+    /// walked directly, row then column then the rest of the block, without scanning the grid.
+    pub fn empty_peers(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (top, left) = (row / 3 * 3, col / 3 * 3);
+        let row_peers = (0..9).filter(move |&c| c != col).map(move |c| (row, c));
+        let col_peers = (0..9).filter(move |&r| r != row).map(move |r| (r, col));
+        let block_peers = (top..top + 3)
+            .filter(move |&r| r != row)
+            .flat_map(move |r| (left..left + 3).filter(move |&c| c != col).map(move |c| (r, c)));
+        row_peers
+            .chain(col_peers)
+            .chain(block_peers)
+            .filter(move |&(r, c)| self.table[r][c] == 0)
     }
 
-    #[test]
-    fn test_invalid_sudoku() {
-        let sudoku: Sudoku = "534678912\n\
-             672195348\n\
-             198342567\n\
-             859761423\n\
-             426853791\n\
-             713924856\n\
-             961537284\n\
-             287419635\n\
-             345286177"
-            .parse()
-            .unwrap();
-        println!("{}", sudoku);
-        assert!(!sudoku.valid());
+    /// Number of filled cells
+    pub fn clue_count(&self) -> usize {
+        self.table.iter().flatten().filter(|&&val| val != 0).count()
     }
 
-    #[test]
-    fn test_invalid_3x3_blocks() {
-        let sudoku: Sudoku = "123456789\n\
-            234567891\n\
-            345678912\n\
-            456789123\n\
-            567891234\n\
-            678912345\n\
-            789123456\n\
-            891234567\n\
-            912345678"
-            .parse()
-            .unwrap();
-        println!("{}", sudoku);
-        assert!(!sudoku.valid());
+    /// Number of empty cells
+    pub fn empty_count(&self) -> usize {
+        81 - self.clue_count()
     }
 
-    #[test]
-    fn test_invalid_cell() {
-        let sudoku: Sudoku = "123456789\n\
-            456789123\n\
-            789123456\n\
-            214365978\n\
-            365897214\n\
-            897214365\n\
-            531642978\n\
-            642978531\n\
-            978531640"
-            .parse()
-            .unwrap();
-        println!("{}", sudoku);
-        assert!(!sudoku.valid());
+    /// Share of filled cells, from 0.0 for an empty grid to 1.0 for a full one. Filled cells
+    /// count whether or not they are correct.
+    pub fn progress(&self) -> f32 {
+        self.clue_count() as f32 / 81.0
+    }
+
+    /// Per-row, per-column and per-block counts of filled cells
+    pub fn clue_distribution(&self) -> ([u8; 9], [u8; 9], [u8; 9]) {
+        let mut rows = [0u8; 9];
+        let mut cols = [0u8; 9];
+        let mut blocks = [0u8; 9];
+        for row in 0..9 {
+            for col in 0..9 {
+                if self.table[row][col] != 0 {
+                    rows[row] += 1;
+                    cols[col] += 1;
+                    blocks[(row / 3) * 3 + col / 3] += 1;
+                }
+            }
+        }
+        (rows, cols, blocks)
+    }
+
+    /// Digits which can be placed in a cell without clashing with its row, column or block.
+    /// Filled cells have no candidates.
+    pub fn candidates(&self, row: usize, col: usize) -> Vec<u8> {
+        digits(self.candidate_mask(row, col)).collect()
+    }
+
+    /// Index `k` counts the empty cells with exactly `k` candidates, so index 0 counts
+    /// contradictions. Filled cells aren't counted.
+    pub fn candidate_histogram(&self) -> [usize; 10] {
+        let cands = self.candidate_grid();
+        let mut histogram = [0; 10];
+        for (row, line) in self.table.iter().enumerate() {
+            for (col, _) in line.iter().enumerate().filter(|(_, &val)| val == 0) {
+                histogram[cands[row][col].count_ones() as usize] += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Candidates of every cell at once, computed from shared row, column and block occupancy
+    pub fn autofill_candidates(&self) -> [[Vec<u8>; 9]; 9] {
+        let mut rows = [0u16; 9];
+        let mut cols = [0u16; 9];
+        let mut blocks = [0u16; 9];
+        for row in 0..9 {
+            for col in 0..9 {
+                let bit = 1 << self.table[row][col];
+                rows[row] |= bit;
+                cols[col] |= bit;
+                if !self.blocks_replaced {
+                    blocks[(row / 3) * 3 + col / 3] |= bit;
+                }
+            }
+        }
+        // occupancy of the custom units, spread onto each of their cells
+        let mut custom = [[0u16; 9]; 9];
+        for cells in &self.custom_units {
+            let used = cells.iter().fold(0u16, |used, &(r, c)| used | 1 << self.table[r][c]);
+            for &(r, c) in cells {
+                custom[r][c] |= used;
+            }
+        }
+        std::array::from_fn(|row| {
+            std::array::from_fn(|col| {
+                if self.table[row][col] != 0 {
+                    return Vec::new();
+                }
+                let used = rows[row] | cols[col] | blocks[(row / 3) * 3 + col / 3] | custom[row][col];
+                digits(self.allowed(row, col) & !used).collect()
+            })
+        })
+    }
+
+    /// Candidates of every cell as a pencil mark layer
+    pub fn computed_marks(&self) -> PencilMarks {
+        PencilMarks { marks: self.candidate_grid() }
+    }
+
+    /// Reads a `.sdm` collection holding one 81 character puzzle per line, blank lines are skipped
+    pub fn read_sdm<R: BufRead>(r: R) -> Result<Vec<Sudoku>, SudokuError> {
+        let mut puzzles = Vec::new();
+        for (i, line) in r.lines().enumerate() {
+            let line = line?;
+            let line = trim_input(&line);
+            if line.is_empty() {
+                continue;
+            }
+            let sudoku = line.parse().map_err(|e: SudokuError| SudokuError {
+                details: format!("Line {}: {}", i + 1, e.details),
+            })?;
+            puzzles.push(sudoku);
+        }
+        Ok(puzzles)
+    }
+
+    /// Reads a single puzzle from a file, see [`FromStr`] for the accepted layout
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Sudoku, SudokuError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| SudokuError {
+            details: format!("{}: {}", path.display(), e),
+        })?;
+        text.parse()
+    }
+
+    /// Reads a `.sdm` collection from a file, see [`Sudoku::read_sdm`]
+    pub fn load_many_from_path<P: AsRef<Path>>(path: P) -> Result<Vec<Sudoku>, SudokuError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| SudokuError {
+            details: format!("{}: {}", path.display(), e),
+        })?;
+        Sudoku::read_sdm(BufReader::new(file))
+    }
+
+    /// Writes puzzles in the `.sdm` format, one line each with `0` for empty cells
+    pub fn write_sdm<W: Write>(puzzles: &[Sudoku], mut w: W) -> io::Result<()> {
+        for sudoku in puzzles {
+            for val in sudoku.table.iter().flatten() {
+                write!(w, "{}", val)?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    /// Empty cell with the fewest candidates, `None` when the grid is full
+    pub fn most_constrained_cell(&self) -> Option<(usize, usize)> {
+        self.most_constrained().map(|(row, col, _)| (row, col))
+    }
+
+    /// Number of empty cells in a unit
+    pub fn empties_in_unit(&self, unit: Unit) -> usize {
+        self.unit_cells(unit).iter().filter(|&&(row, col)| self.table[row][col] == 0).count()
+    }
+
+    /// Unit with the fewest empty cells which isn't complete yet
+    pub fn most_constrained_unit(&self) -> Option<Unit> {
+        self.units()
+            .into_iter()
+            .map(|(unit, _)| (unit, self.empties_in_unit(unit)))
+            .filter(|&(_, empties)| empties > 0)
+            .min_by_key(|&(_, empties)| empties)
+            .map(|(unit, _)| unit)
+    }
+
+    /// Solves the puzzle in place using the default constraint propagation strategy
+    pub fn solve(&mut self) -> bool {
+        self.solve_with(Strategy::ConstraintPropagation)
+    }
+
+    /// Solves the puzzle in place with the given strategy, returning whether a solution was found.
+    /// The grid is left untouched when solving fails.
+    pub fn solve_with(&mut self, strategy: Strategy) -> bool {
+        if !self.consistent() {
+            return false;
+        }
+        let units = self.units();
+        let solved = match strategy {
+            Strategy::Backtrack => self.backtrack(&units),
+            Strategy::ConstraintPropagation => self.search(&units, &mut Budget::unlimited(), &mut |_| true),
+            Strategy::HumanTechniquesOnly => {
+                let attempt = self.logical_solve().grid;
+                let solved = attempt.valid();
+                if solved {
+                    *self = attempt;
+                }
+                solved
+            }
+        };
+        self.assert_invariants();
+        solved
+    }
+
+    /// Solves the puzzle in place making at most `budget` guesses. When the budget runs out the
+    /// grid is left in its most propagated consistent state, so callers can show the progress.
+    pub fn solve_with_budget(&mut self, budget: usize) -> SolveOutcome {
+        self.solve_limited(Budget::new(budget))
+    }
+
+    /// Solves the puzzle in place with at most `max_depth` guesses nested inside each other,
+    /// leaving the grid as `solve_with_budget` does when the limit is hit. A 9x9 search never
+    /// nests more than 81 guesses, the limit guards variants and bigger grids.
+    pub fn solve_with_max_depth(&mut self, max_depth: usize) -> SolveOutcome {
+        self.solve_limited(Budget::unlimited().with_max_depth(max_depth))
+    }
+
+    fn solve_limited(&mut self, mut budget: Budget) -> SolveOutcome {
+        if !self.consistent() {
+            return SolveOutcome::Unsolvable;
+        }
+        let units = self.units();
+        let mut trail = Vec::new();
+        let mut outcome = SolveOutcome::Unsolvable;
+        if self.propagate(&units, &mut trail) {
+            if self.search(&units, &mut budget, &mut |_| true) {
+                outcome = SolveOutcome::Solved;
+            } else if budget.exceeded {
+                outcome = SolveOutcome::BudgetExceeded;
+            } else if budget.too_deep {
+                outcome = SolveOutcome::DepthExceeded;
+            }
+        }
+        if outcome == SolveOutcome::Unsolvable {
+            for (row, col) in trail {
+                self.table[row][col] = 0;
+            }
+        }
+        self.assert_invariants();
+        outcome
+    }
+
+    /// Copy of the grid with `val` assumed at an empty cell and naked and hidden singles
+    /// propagated from there, for exploring "what if" branches. Errors if the cell can't take
+    /// the value or the propagation runs into a contradiction.
+    pub fn assume(&self, row: usize, col: usize, val: u8) -> Result<Sudoku, SudokuError> {
+        if row >= 9 || col >= 9 {
+            return Err(SudokuError{details: format!("Cell {},{} is outside the grid.", row, col)});
+        }
+        if !(1..=9).contains(&val) {
+            return Err(SudokuError{details: format!("Invalid value {} at {},{}.", val, row, col)});
+        }
+        if self.table[row][col] != 0 {
+            return Err(SudokuError{details: format!("Cell {},{} is already filled.", row, col)});
+        }
+        if self.candidate_mask(row, col) & (1 << val) == 0 {
+            return Err(SudokuError{details: format!("Value {} at {},{} clashes with a peer.", val, row, col)});
+        }
+        let mut sudoku = self.clone();
+        sudoku.table[row][col] = val;
+        let units = sudoku.units();
+        if !sudoku.propagate(&units, &mut Vec::new()) {
+            return Err(SudokuError{details: format!("Assuming {} at {},{} leads to a contradiction.", val, row, col)});
+        }
+        Ok(sudoku)
+    }
+
+    /// Solves the puzzle in place by simulated annealing, minimising `count_conflicts`. Every
+    /// block is filled with its missing digits in random order, then two free cells of a block
+    /// are swapped at a time; a swap that adds conflicts is still taken with a probability that
+    /// shrinks as the temperature cools, and the temperature is reset when the search stalls.
+    /// Grids whose blocks were replaced are filled row by row instead. Returns false, leaving
+    /// the grid untouched, if no solution was found within the step limit.
+    pub fn solve_annealing(&mut self, seed: u64) -> bool {
+        const MAX_STEPS: usize = 200_000;
+        const START_TEMPERATURE: f64 = 0.5;
+        const COOLING: f64 = 0.9995;
+        const STALL: usize = 5_000;
+        if !self.consistent() {
+            return false;
+        }
+        let mut rng = Rng::new(seed);
+        let boxes: Vec<[(usize, usize); 9]> = (0..9)
+            .map(|i| if self.blocks_replaced { Unit::Row(i) } else { Unit::Block(i) }.cells())
+            .collect();
+        let mut sudoku = self.clone();
+        let mut free: Vec<Vec<(usize, usize)>> = Vec::with_capacity(9);
+        for cells in &boxes {
+            let taken = cells.iter().fold(0u16, |mask, &(row, col)| mask | 1 << sudoku.table[row][col]);
+            let mut missing: Vec<u8> = digits(ALL_DIGITS & !taken).collect();
+            rng.shuffle(&mut missing);
+            let empty: Vec<(usize, usize)> = cells.iter().copied().filter(|&(row, col)| sudoku.table[row][col] == 0).collect();
+            for (&(row, col), val) in empty.iter().zip(missing) {
+                sudoku.table[row][col] = val;
+            }
+            if empty.len() >= 2 {
+                free.push(empty);
+            }
+        }
+        let mut energy = sudoku.count_conflicts();
+        let mut temperature = START_TEMPERATURE;
+        let mut since_best = 0;
+        let mut best = energy;
+        for _ in 0..MAX_STEPS {
+            if energy == 0 || free.is_empty() {
+                break;
+            }
+            let cells = &free[rng.below(free.len())];
+            let a = cells[rng.below(cells.len())];
+            let b = cells[rng.below(cells.len())];
+            if a == b {
+                continue;
+            }
+            swap_cells(&mut sudoku.table, a, b);
+            let next = sudoku.count_conflicts();
+            let accept = next <= energy
+                || ((energy as f64 - next as f64) / temperature).exp() > (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+            if accept {
+                energy = next;
+            } else {
+                swap_cells(&mut sudoku.table, a, b);
+            }
+            if energy < best {
+                best = energy;
+                since_best = 0;
+            } else {
+                since_best += 1;
+            }
+            if since_best > STALL {
+                temperature = START_TEMPERATURE;
+                since_best = 0;
+                best = energy;
+            } else {
+                temperature = (temperature * COOLING).max(0.01);
+            }
+        }
+        if energy != 0 || !sudoku.valid() {
+            return false;
+        }
+        self.table = sudoku.table;
+        self.assert_invariants();
+        true
+    }
+
+    /// Checks the internal state for corruption: values out of range, empty givens, digits
+    /// repeated within a unit, broken cell constraints or bulk candidates disagreeing with the
+    /// per-cell computation
+    pub fn check_invariants(&self) -> Result<(), String> {
+        // candidates are only computed once every value is known to be a digit
+        for (row, line) in self.table.iter().enumerate() {
+            for (col, &val) in line.iter().enumerate() {
+                if val > 9 {
+                    return Err(format!("Cell {},{} holds invalid value {}.", row, col, val));
+                }
+                if self.givens[row][col] && val == 0 {
+                    return Err(format!("Given cell {},{} is empty.", row, col));
+                }
+            }
+        }
+        let bulk = self.autofill_candidates();
+        for (row, line) in bulk.iter().enumerate() {
+            for (col, cands) in line.iter().enumerate() {
+                if *cands != self.candidates(row, col) {
+                    return Err(format!("Candidates of cell {},{} disagree.", row, col));
+                }
+            }
+        }
+        if !self.parity_respected() {
+            return Err("A cell breaks its parity constraint.".to_string());
+        }
+        for (unit, cells) in self.units() {
+            let mut seen = 0u16;
+            for (row, col) in cells {
+                let val = self.table[row][col];
+                if val != 0 && seen & (1 << val) != 0 {
+                    return Err(format!("Digit {} appears twice in {}.", val, unit));
+                }
+                seen |= 1 << val;
+            }
+        }
+        Ok(())
+    }
+
+    /// Panics on broken invariants in debug builds, called at the solver boundaries
+    fn assert_invariants(&self) {
+        #[cfg(debug_assertions)]
+        if let Err(details) = self.check_invariants() {
+            panic!("sudoku invariant violated: {}", details);
+        }
+    }
+
+    /// Returns true if the puzzle has exactly one solution
+    pub fn has_unique_solution(&self) -> bool {
+        self.solution_count(2) == 1
+    }
+
+    /// Solved copy of the puzzle, `None` if it has no solution. The copy keeps the puzzle's
+    /// clue mask, only the original clues are givens, and `self` is left untouched.
+    pub fn solution(&self) -> Option<Sudoku> {
+        let mut solved = self.clone();
+        solved.history.clear();
+        solved.undone.clear();
+        solved.solve().then_some(solved)
+    }
+
+    /// Returns true for a well-formed publishable puzzle: consistent clues, a unique solution
+    /// and at least 17 clues
+    pub fn is_proper(&self) -> bool {
+        self.clue_count() >= 17 && self.consistent() && self.has_unique_solution()
+    }
+
+    /// Number of solutions, counting stops once `cap` is reached
+    pub fn solution_count(&self, cap: usize) -> usize {
+        let mut count = 0;
+        if cap == 0 {
+            return count;
+        }
+        self.for_each_solution(&mut |_| {
+            count += 1;
+            count >= cap
+        });
+        count
+    }
+
+    /// Up to `cap` solutions of the puzzle
+    pub fn solutions(&self, cap: usize) -> Vec<Sudoku> {
+        let mut found = Vec::new();
+        if cap == 0 {
+            return found;
+        }
+        self.for_each_solution(&mut |solution| {
+            found.push(solution.clone());
+            found.len() >= cap
+        });
+        found
+    }
+
+    /// Every completion of the grid, up to `cap`. Unlike `solutions` the grids are guaranteed
+    /// distinct and sorted by their row-major values, as found by a plain depth-first search.
+    pub fn enumerate(&self, cap: usize) -> Vec<Sudoku> {
+        let mut found = Vec::new();
+        if cap > 0 && self.consistent() {
+            let units = self.units();
+            self.clone().enumerate_from(0, &units, cap, &mut found);
+        }
+        found
+    }
+
+    fn enumerate_from(&mut self, start: usize, units: &[(Unit, [(usize, usize); 9])], cap: usize, found: &mut Vec<Sudoku>) {
+        if self.dead_end(units) {
+            return;
+        }
+        let Some(i) = (start..81).find(|&i| self.table[i / 9][i % 9] == 0) else {
+            found.push(self.clone());
+            return;
+        };
+        let (row, col) = (i / 9, i % 9);
+        for val in digits(self.candidate_mask(row, col)) {
+            self.table[row][col] = val;
+            self.enumerate_from(i + 1, units, cap, found);
+            if found.len() >= cap {
+                break;
+            }
+        }
+        self.table[row][col] = 0;
+    }
+
+    /// Calls `on_solution` for each solution until it returns true
+    fn for_each_solution(&self, on_solution: &mut dyn FnMut(&Sudoku) -> bool) {
+        if !self.consistent() {
+            return;
+        }
+        let units = self.units();
+        self.clone().search(&units, &mut Budget::unlimited(), on_solution);
+    }
+
+    /// Value of a cell, 0 when empty
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.table[row][col]
+    }
+
+    /// Coordinates of every cell for which `f(row, col, value)` holds, in row-major order
+    pub fn cells_matching<F: Fn(usize, usize, u8) -> bool>(&self, f: F) -> Vec<(usize, usize)> {
+        (0..81)
+            .map(|i| (i / 9, i % 9))
+            .filter(|&(row, col)| f(row, col, self.table[row][col]))
+            .collect()
+    }
+
+    /// Returns true if the cell was filled in the original puzzle
+    pub fn is_given(&self, row: usize, col: usize) -> bool {
+        self.givens[row][col]
+    }
+
+    /// Plays a move, writing `val` (0 to erase) into a non-given cell. The move is recorded
+    /// for `undo` and discards any moves available to `redo`.
+    pub fn set(&mut self, row: usize, col: usize, val: u8) -> Result<(), SudokuError> {
+        if row >= 9 || col >= 9 {
+            return Err(SudokuError{details: format!("Cell {},{} is outside the grid.", row, col)});
+        }
+        if val > 9 {
+            return Err(SudokuError{details: format!("Invalid value {} at {},{}.", val, row, col)});
+        }
+        if self.givens[row][col] {
+            return Err(SudokuError{details: format!("Cell {},{} is a given.", row, col)});
+        }
+        let old = self.table[row][col];
+        self.table[row][col] = val;
+        self.history.push(Move { row, col, old, new: val });
+        self.undone.clear();
+        Ok(())
+    }
+
+    /// Plays a batch of moves as with `set`, all or nothing: if a move is rejected or puts a
+    /// digit twice into a unit, every earlier move of the batch is reverted, history included,
+    /// and the error names the failing move counting from 1.
+    pub fn set_many(&mut self, moves: &[(usize, usize, u8)]) -> Result<(), SudokuError> {
+        let (table, history, undone) = (self.table, self.history.clone(), self.undone.clone());
+        let units = self.units();
+        for (i, &(row, col, val)) in moves.iter().enumerate() {
+            let result = self.set(row, col, val).and_then(|_| {
+                let clash = units.iter().find(|(_, cells)| {
+                    val != 0
+                        && cells.contains(&(row, col))
+                        && cells.iter().any(|&(r, c)| (r, c) != (row, col) && self.table[r][c] == val)
+                });
+                match clash {
+                    Some((unit, _)) => Err(SudokuError{details: format!("Digit {} appears twice in {} at {},{}.", val, unit, row, col)}),
+                    None => Ok(()),
+                }
+            });
+            if let Err(e) = result {
+                self.table = table;
+                self.history = history;
+                self.undone = undone;
+                return Err(SudokuError{details: format!("Move {}: {}", i + 1, e.details)});
+            }
+        }
+        Ok(())
+    }
+
+    /// Reverts the last move, returning false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(m) => {
+                self.table[m.row][m.col] = m.old;
+                self.undone.push(m);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replays the last undone move, returning false if there was nothing to redo
+    pub fn redo(&mut self) -> bool {
+        match self.undone.pop() {
+            Some(m) => {
+                self.table[m.row][m.col] = m.new;
+                self.history.push(m);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Empties a cell, also removing it from the givens
+    pub fn clear(&mut self, row: usize, col: usize) {
+        self.table[row][col] = 0;
+        self.givens[row][col] = false;
+    }
+
+    /// Empties a cell and returns whether the puzzle still has a unique solution
+    pub fn remove_clue(&mut self, row: usize, col: usize) -> bool {
+        self.clear(row, col);
+        self.has_unique_solution()
+    }
+
+    /// Generates a puzzle with exactly `target` clues and a unique solution, starting from a
+    /// random full grid and removing clues in random order. Returns `None` if the grid can't be
+    /// reduced that far without losing uniqueness.
+    pub fn generate_with_clues(target: usize, seed: u64) -> Option<Sudoku> {
+        let sudoku = Sudoku::random_puzzle(target, &mut Rng::new(seed));
+        (sudoku.clue_count() == target).then_some(sudoku)
+    }
+
+    /// Generates a minimal puzzle: a random full grid from which clues are removed in random
+    /// order whenever uniqueness allows. A clue kept in that pass stays necessary as others go,
+    /// so no clue of the result is redundant.
+    pub fn random_minimal(seed: u64) -> Sudoku {
+        Sudoku::random_puzzle(0, &mut Rng::new(seed))
+    }
+
+    /// Returns true if the puzzle has a unique solution which is lost by removing any clue
+    pub fn is_minimal(&self) -> bool {
+        if !self.has_unique_solution() {
+            return false;
+        }
+        let mut sudoku = self.clone();
+        (0..81).map(|i| (i / 9, i % 9)).all(|(row, col)| {
+            let val = sudoku.table[row][col];
+            if val == 0 {
+                return true;
+            }
+            sudoku.table[row][col] = 0;
+            let unique = sudoku.has_unique_solution();
+            sudoku.table[row][col] = val;
+            !unique
+        })
+    }
+
+    /// Generates a puzzle of the requested difficulty, with a clue count in the matching band
+    /// such as [`EASY_CLUES`]. Clue removal is random and can stall above the band, so candidate
+    /// puzzles are rated and regenerated until one matches on both, up to a bounded number of
+    /// attempts. When no attempt hits the requested difficulty the closest puzzle found is
+    /// returned, its `rating` then differs from `difficulty`.
+    pub fn generate(difficulty: Difficulty, seed: u64) -> Generated {
+        const MAX_ATTEMPTS: usize = 32;
+        let clues = match difficulty {
+            Difficulty::Easy => EASY_CLUES,
+            Difficulty::Medium => MEDIUM_CLUES,
+            Difficulty::Hard => HARD_CLUES,
+            Difficulty::Expert => EXPERT_CLUES,
+        };
+        let mut rng = Rng::new(seed);
+        let mut best: Option<((usize, usize), Sudoku, Difficulty)> = None;
+        let mut attempts = 0;
+        while attempts < MAX_ATTEMPTS {
+            attempts += 1;
+            let target = clues.start() + rng.below(clues.end() - clues.start() + 1);
+            let puzzle = Sudoku::random_puzzle(target, &mut rng);
+            let rating = puzzle.rate();
+            // removal can stall above the band, such puzzles only win if nothing better is found
+            let excess = puzzle.clue_count().saturating_sub(*clues.end());
+            let distance = ((rating as usize).abs_diff(difficulty as usize), excess);
+            if best.as_ref().is_none_or(|(d, _, _)| distance < *d) {
+                best = Some((distance, puzzle, rating));
+            }
+            if distance == (0, 0) {
+                break;
+            }
+        }
+        let (_, puzzle, rating) = best.expect("at least one attempt is made");
+        Generated { puzzle, rating, attempts }
+    }
+
+    /// Rates the puzzle by the hardest logical technique needed to solve it, puzzles which
+    /// can't be solved without guessing (including invalid ones) are `Expert`
+    pub fn rate(&self) -> Difficulty {
+        let profile = self.complexity_profile();
+        let mut solved = self.clone();
+        if !solved.solve_with(Strategy::HumanTechniquesOnly) {
+            return Difficulty::Expert;
+        }
+        if profile.contains_key(&Technique::NakedPair) {
+            Difficulty::Hard
+        } else if profile.contains_key(&Technique::PointingPair) || profile.contains_key(&Technique::BoxLineReduction) {
+            Difficulty::Medium
+        } else {
+            Difficulty::Easy
+        }
+    }
+
+    /// Returns true if this puzzle rates as strictly harder than `other`
+    pub fn harder_than(&self, other: &Sudoku) -> bool {
+        self.rate() > other.rate()
+    }
+
+    /// Random puzzle with a unique solution, removing clues from a random full grid in random
+    /// order until only `target` are left or no more can go without losing uniqueness
+    fn random_puzzle(target: usize, rng: &mut Rng) -> Sudoku {
+        let mut sudoku = Sudoku::default();
+        sudoku.fill_random(rng);
+        let mut cells: Vec<(usize, usize)> = (0..81).map(|i| (i / 9, i % 9)).collect();
+        rng.shuffle(&mut cells);
+        let mut clues = 81;
+        for (row, col) in cells {
+            if clues <= target {
+                break;
+            }
+            let val = sudoku.table[row][col];
+            sudoku.table[row][col] = 0;
+            if sudoku.has_unique_solution() {
+                clues -= 1;
+            } else {
+                sudoku.table[row][col] = val;
+            }
+        }
+        Sudoku::from_table(sudoku.table)
+    }
+
+    /// Fills the empty cells with a random valid completion
+    fn fill_random(&mut self, rng: &mut Rng) -> bool {
+        let Some(i) = (0..81).find(|&i| self.table[i / 9][i % 9] == 0) else {
+            return true;
+        };
+        let (row, col) = (i / 9, i % 9);
+        let mut vals: Vec<u8> = digits(self.candidate_mask(row, col)).collect();
+        rng.shuffle(&mut vals);
+        for val in vals {
+            self.table[row][col] = val;
+            if self.fill_random(rng) {
+                return true;
+            }
+        }
+        self.table[row][col] = 0;
+        false
+    }
+
+    /// Next move for a player, found with the simplest applicable technique. Eliminations
+    /// needed before a value can be placed are mentioned in the explanation. When no logical
+    /// move exists the hint suggests guessing in the most constrained cell, taking the value
+    /// from the solution when it is unique. Returns `None` for full or contradictory grids.
+    pub fn next_hint(&self) -> Option<Hint> {
+        if !self.consistent() {
+            return None;
+        }
+        let mut eliminations: Vec<String> = Vec::new();
+        for deduction in self.logical_solve().deductions {
+            let Some((row, col, value)) = deduction.placed else {
+                eliminations.push(deduction.to_string());
+                continue;
+            };
+            let mut explanation = deduction.to_string();
+            if !eliminations.is_empty() {
+                explanation = format!("After eliminating candidates with {}: {}", eliminations.join(", "), explanation);
+            }
+            return Some(Hint { row, col, value, technique: Some(deduction.technique), explanation });
+        }
+        let (row, col, mask) = self.most_constrained()?;
+        // a unique solution tells which candidate is right, otherwise any of them may be
+        let value = match self.solution().filter(|_| self.has_unique_solution()) {
+            Some(solution) => solution.table[row][col],
+            None => digits(mask).next()?,
+        };
+        let options: Vec<String> = digits(mask).map(|d| d.to_string()).collect();
+        Some(Hint {
+            row,
+            col,
+            value,
+            technique: None,
+            explanation: format!(
+                "No logical move found, guess {} in {} (candidates {})",
+                value,
+                cell_name(row, col),
+                options.join(", ")
+            ),
+        })
+    }
+
+    /// Narrates a human-style solve as numbered steps, e.g. `1. R1C3 = 4 (naked single); 2. ...`.
+    /// Candidate eliminations get their own steps. When the techniques run out before the grid
+    /// is full the narration ends by saying that guessing is required, or that the puzzle is
+    /// contradictory if an empty cell is left without candidates.
+    pub fn explain(&self) -> String {
+        if !self.consistent() {
+            return "The puzzle breaks the rules, it has no solution.".to_string();
+        }
+        let solve = self.logical_solve();
+        let mut steps: Vec<String> = solve
+            .deductions
+            .iter()
+            .map(|deduction| match deduction.placed {
+                Some(_) => deduction.to_string(),
+                None => format!("eliminate candidates ({})", deduction),
+            })
+            .collect();
+        if solve.contradiction {
+            steps.push("an empty cell has no candidates left, the puzzle is contradictory".to_string());
+        } else if !solve.grid.is_complete() {
+            steps.push("no technique applies, guessing is required".to_string());
+        }
+        steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| format!("{}. {}", i + 1, step))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// Scores every originally empty cell by how late the technique-based solve fills it,
+    /// scaled to `1..=255` so the last cell placed scores 255. Cells the techniques can't reach
+    /// also score 255, givens score 0.
+    pub fn difficulty_heatmap(&self) -> [[u8; 9]; 9] {
+        let mut heatmap = [[0; 9]; 9];
+        let empty = self.empty_count();
+        for (row, line) in self.table.iter().enumerate() {
+            for (col, _) in line.iter().enumerate().filter(|(_, &val)| val == 0) {
+                heatmap[row][col] = u8::MAX;
+            }
+        }
+        let placed = self.logical_solve().deductions.into_iter().filter_map(|deduction| deduction.placed);
+        for (order, (row, col, _)) in placed.enumerate() {
+            heatmap[row][col] = ((order + 1) * usize::from(u8::MAX) / empty) as u8;
+        }
+        heatmap
+    }
+
+    /// Counts how many times each logical technique fires while solving a copy of the puzzle.
+    /// Solving stops where the techniques run out, so unsolvable puzzles give a partial profile.
+    pub fn complexity_profile(&self) -> HashMap<Technique, usize> {
+        let mut profile = HashMap::new();
+        for deduction in self.logical_solve().deductions {
+            *profile.entry(deduction.technique).or_insert(0) += 1;
+        }
+        profile
+    }
+
+    /// Applies logical techniques to a copy of the grid until none makes progress, recording
+    /// each deduction in order. Grids breaking the rules are left as they are.
+    fn logical_solve(&self) -> LogicalSolve {
+        let mut grid = self.clone();
+        let mut deductions = Vec::new();
+        if !self.consistent() {
+            return LogicalSolve { deductions, grid, contradiction: true };
+        }
+        let units = grid.units();
+        let mut cands = grid.candidate_grid();
+        while let Some(deduction) = grid.apply_technique(&mut cands, &units) {
+            deductions.push(deduction);
+        }
+        let contradiction = (0..81).any(|i| grid.table[i / 9][i % 9] == 0 && cands[i / 9][i % 9] == 0);
+        LogicalSolve { deductions, grid, contradiction }
+    }
+
+    /// Random equivalent puzzle: digits are relabeled, bands, stacks and the lines within them
+    /// reordered and the grid possibly transposed, so solvability and uniqueness are kept.
+    /// Parity marks and custom units are not carried over.
+    pub fn shuffle(&self, seed: u64) -> Sudoku {
+        let mut rng = Rng::new(seed);
+        let mut relabel: [u8; 10] = std::array::from_fn(|d| d as u8);
+        rng.shuffle(&mut relabel[1..]);
+        let mut lines = || -> [usize; 9] {
+            let mut bands = [0, 1, 2];
+            rng.shuffle(&mut bands);
+            let mut order = [0; 9];
+            for (i, band) in bands.into_iter().enumerate() {
+                let mut inner = [0, 1, 2];
+                rng.shuffle(&mut inner);
+                for (j, line) in inner.into_iter().enumerate() {
+                    order[i * 3 + j] = band * 3 + line;
+                }
+            }
+            order
+        };
+        let (rows, cols) = (lines(), lines());
+        let mut table: [[u8; 9]; 9] = std::array::from_fn(|r| std::array::from_fn(|c| relabel[self.table[rows[r]][cols[c]] as usize]));
+        if rng.below(2) == 1 {
+            table = transposed(&table);
+        }
+        Sudoku::from_table(table)
+    }
+
+    /// Returns true if some combination of digit relabeling, band, stack, row and column
+    /// permutations and transposition turns this grid into `other`
+    pub fn is_isomorphic_to(&self, other: &Sudoku) -> bool {
+        if self.clue_count() != other.clue_count() {
+            return false;
+        }
+        let perms = line_permutations();
+        for target in [other.table, transposed(&other.table)] {
+            for perm in &perms {
+                let grid: [[u8; 9]; 9] = std::array::from_fn(|r| std::array::from_fn(|c| target[r][perm[c]]));
+                if match_rows(&self.table, &grid, 0, [None; 3], [false; 9], [0; 10], [0; 10]) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// All units constraining the grid
+    fn units(&self) -> Vec<(Unit, [(usize, usize); 9])> {
+        (0..9)
+            .map(Unit::Row)
+            .chain((0..9).map(Unit::Column))
+            .chain((0..9).map(Unit::Block).filter(|_| !self.blocks_replaced))
+            .map(|unit| (unit, unit.cells()))
+            .chain(self.custom_units.iter().enumerate().map(|(i, &cells)| (Unit::Custom(i), cells)))
+            .collect()
+    }
+
+    /// Returns true if no unit holds the same digit twice and every filled cell satisfies its
+    /// own constraints, empty cells are ignored
+    fn consistent(&self) -> bool {
+        self.parity_respected() && self.units().iter().all(|(_, cells)| self.no_repeats(cells))
+    }
+
+    /// Returns true if the cells hold no digit twice, empty cells are ignored
+    fn no_repeats(&self, cells: &[(usize, usize)]) -> bool {
+        let mut seen = 0u16;
+        for &(row, col) in cells {
+            let val = self.table[row][col];
+            if val != 0 {
+                if seen & (1 << val) != 0 {
+                    return false;
+                }
+                seen |= 1 << val;
+            }
+        }
+        true
+    }
+
+    /// Bitset of the digits which can be placed in an empty cell, zero for filled cells
+    fn candidate_mask(&self, row: usize, col: usize) -> u16 {
+        if self.table[row][col] != 0 {
+            return 0;
+        }
+        let mut used = 0u16;
+        for i in 0..9 {
+            used |= 1 << self.table[row][i];
+            used |= 1 << self.table[i][col];
+            if !self.blocks_replaced {
+                used |= 1 << self.table[(row / 3) * 3 + i / 3][(col / 3) * 3 + i % 3];
+            }
+        }
+        for cells in self.custom_units.iter().filter(|cells| cells.contains(&(row, col))) {
+            for &(r, c) in cells {
+                used |= 1 << self.table[r][c];
+            }
+        }
+        self.allowed(row, col) & !used
+    }
+
+    /// Returns true if an empty cell has no candidate left or a unit can no longer fit one of
+    /// its missing digits, meaning the grid can't be completed
+    fn dead_end(&self, units: &[(Unit, [(usize, usize); 9])]) -> bool {
+        units.iter().any(|(_, cells)| {
+            let mut possible = 0u16;
+            for &(row, col) in cells {
+                let val = self.table[row][col];
+                if val != 0 {
+                    possible |= 1 << val;
+                } else {
+                    let mask = self.candidate_mask(row, col);
+                    if mask == 0 {
+                        return true;
+                    }
+                    possible |= mask;
+                }
+            }
+            possible != ALL_DIGITS
+        })
+    }
+
+    /// Depth-first search trying every candidate of the first empty cell, abandoning a branch
+    /// as soon as it reaches a dead end
+    fn backtrack(&mut self, units: &[(Unit, [(usize, usize); 9])]) -> bool {
+        if self.dead_end(units) {
+            return false;
+        }
+        for row in 0..9 {
+            for col in 0..9 {
+                if self.table[row][col] != 0 {
+                    continue;
+                }
+                for val in digits(self.candidate_mask(row, col)) {
+                    self.table[row][col] = val;
+                    if self.backtrack(units) {
+                        return true;
+                    }
+                }
+                self.table[row][col] = 0;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Propagates singles then guesses on the most constrained cell, calling `on_solution` for
+    /// every completed grid until it returns true. Each guess consumes one unit of `budget` and
+    /// nesting is bounded by its depth limit, the search gives up once either is hit. Only a
+    /// solution accepted by `on_solution` stays on the grid; rejected solutions, exhausted
+    /// branches and a search stopped by the budget or depth limit undo everything they placed.
+    fn search(
+        &mut self,
+        units: &[(Unit, [(usize, usize); 9])],
+        budget: &mut Budget,
+        on_solution: &mut dyn FnMut(&Sudoku) -> bool,
+    ) -> bool {
+        let mut trail = Vec::new();
+        if self.propagate(units, &mut trail) {
+            match self.most_constrained() {
+                None => {
+                    if on_solution(self) {
+                        return true;
+                    }
+                }
+                Some(_) if budget.depth_left == 0 => budget.too_deep = true,
+                Some((row, col, mask)) => {
+                    budget.depth_left -= 1;
+                    for val in digits(mask) {
+                        if !budget.spend() {
+                            break;
+                        }
+                        self.table[row][col] = val;
+                        if self.search(units, budget, on_solution) {
+                            return true;
+                        }
+                        if budget.stopped() {
+                            break;
+                        }
+                    }
+                    budget.depth_left += 1;
+                    self.table[row][col] = 0;
+                }
+            }
+        }
+        for (row, col) in trail {
+            self.table[row][col] = 0;
+        }
+        false
+    }
+
+    /// Empty cell with the fewest candidates along with its candidate bitset
+    fn most_constrained(&self) -> Option<(usize, usize, u16)> {
+        let mut best: Option<(usize, usize, u16)> = None;
+        for row in 0..9 {
+            for col in 0..9 {
+                if self.table[row][col] != 0 {
+                    continue;
+                }
+                let mask = self.candidate_mask(row, col);
+                if best.is_none_or(|(_, _, b)| mask.count_ones() < b.count_ones()) {
+                    best = Some((row, col, mask));
+                }
+            }
+        }
+        best
+    }
+
+    /// Fills naked and hidden singles until none are left, recording filled cells in `trail`.
+    /// Returns false as soon as a cell or a unit runs out of options.
+    fn propagate(&mut self, units: &[(Unit, [(usize, usize); 9])], trail: &mut Vec<(usize, usize)>) -> bool {
+        loop {
+            let mut progress = false;
+            // naked singles
+            for row in 0..9 {
+                for col in 0..9 {
+                    if self.table[row][col] != 0 {
+                        continue;
+                    }
+                    let mask = self.candidate_mask(row, col);
+                    if mask == 0 {
+                        return false;
+                    }
+                    if mask.count_ones() == 1 {
+                        self.table[row][col] = mask.trailing_zeros() as u8;
+                        trail.push((row, col));
+                        progress = true;
+                    }
+                }
+            }
+            // hidden singles
+            for (_, cells) in units {
+                let mut placed = 0u16;
+                let mut once = 0u16;
+                let mut more = 0u16;
+                for &(row, col) in cells {
+                    let val = self.table[row][col];
+                    if val != 0 {
+                        placed |= 1 << val;
+                    } else {
+                        let mask = self.candidate_mask(row, col);
+                        more |= once & mask;
+                        once |= mask;
+                    }
+                }
+                if placed | once != ALL_DIGITS {
+                    return false;
+                }
+                for val in digits(once & !more & !placed) {
+                    let &(row, col) = cells
+                        .iter()
+                        .find(|&&(r, c)| self.table[r][c] == 0 && self.candidate_mask(r, c) & (1 << val) != 0)
+                        .unwrap_or(&(9, 9));
+                    if row == 9 {
+                        return false;
+                    }
+                    self.table[row][col] = val;
+                    trail.push((row, col));
+                    progress = true;
+                }
+            }
+            if !progress {
+                return true;
+            }
+        }
+    }
+
+    /// Candidate bitsets of every cell, as computed from the placed values
+    fn candidate_grid(&self) -> [[u16; 9]; 9] {
+        let mut cands = [[0u16; 9]; 9];
+        for (row, line) in cands.iter_mut().enumerate() {
+            for (col, cell) in line.iter_mut().enumerate() {
+                *cell = self.candidate_mask(row, col);
+            }
+        }
+        cands
+    }
+
+    /// Places a value and removes it from the candidates of every cell sharing a unit with it
+    fn place_logical(
+        &mut self,
+        cands: &mut [[u16; 9]; 9],
+        units: &[(Unit, [(usize, usize); 9])],
+        row: usize,
+        col: usize,
+        val: u8,
+    ) {
+        self.table[row][col] = val;
+        cands[row][col] = 0;
+        for (_, cells) in units.iter().filter(|(_, cells)| cells.contains(&(row, col))) {
+            for &(r, c) in cells {
+                cands[r][c] &= !(1 << val);
+            }
+        }
+    }
+
+    /// Applies the simplest technique making progress, returning it or `None` when stuck
+    fn apply_technique(
+        &mut self,
+        cands: &mut [[u16; 9]; 9],
+        units: &[(Unit, [(usize, usize); 9])],
+    ) -> Option<Deduction> {
+        // an empty cell without candidates can't be resolved by any technique
+        if (0..81).any(|i| self.table[i / 9][i % 9] == 0 && cands[i / 9][i % 9] == 0) {
+            return None;
+        }
+        // naked single
+        let single = (0..81).map(|i| (i / 9, i % 9)).find(|&(r, c)| cands[r][c].count_ones() == 1);
+        if let Some((row, col)) = single {
+            let val = cands[row][col].trailing_zeros() as u8;
+            self.place_logical(cands, units, row, col, val);
+            return Some(Deduction { technique: Technique::NakedSingle, unit: None, placed: Some((row, col, val)) });
+        }
+        // hidden single
+        for &(unit, cells) in units {
+            for val in 1..=9u8 {
+                let mut spots = cells.iter().filter(|&&(r, c)| cands[r][c] & (1 << val) != 0);
+                if let (Some(&(row, col)), None) = (spots.next(), spots.next()) {
+                    self.place_logical(cands, units, row, col, val);
+                    return Some(Deduction { technique: Technique::HiddenSingle, unit: Some(unit), placed: Some((row, col, val)) });
+                }
+            }
+        }
+        // pointing pairs and box/line reductions
+        for &(unit, cells) in units {
+            for val in 1..=9u8 {
+                let spots: Vec<(usize, usize)> =
+                    cells.iter().copied().filter(|&(r, c)| cands[r][c] & (1 << val) != 0).collect();
+                if spots.len() < 2 {
+                    continue;
+                }
+                for (other, other_cells) in units {
+                    if *other == unit || !spots.iter().all(|spot| other_cells.contains(spot)) {
+                        continue;
+                    }
+                    let mut eliminated = false;
+                    for &(r, c) in other_cells {
+                        if !spots.contains(&(r, c)) && cands[r][c] & (1 << val) != 0 {
+                            cands[r][c] &= !(1 << val);
+                            eliminated = true;
+                        }
+                    }
+                    if eliminated {
+                        let technique = match unit {
+                            Unit::Block(_) | Unit::Custom(_) => Technique::PointingPair,
+                            _ => Technique::BoxLineReduction,
+                        };
+                        return Some(Deduction { technique, unit: Some(unit), placed: None });
+                    }
+                }
+            }
+        }
+        // naked pairs
+        for &(unit, cells) in units {
+            for (i, &(r1, c1)) in cells.iter().enumerate() {
+                let pair = cands[r1][c1];
+                if pair.count_ones() != 2 {
+                    continue;
+                }
+                for &(r2, c2) in &cells[i + 1..] {
+                    if cands[r2][c2] != pair {
+                        continue;
+                    }
+                    let mut eliminated = false;
+                    for (r, c) in cells {
+                        if (r, c) != (r1, c1) && (r, c) != (r2, c2) && cands[r][c] & pair != 0 {
+                            cands[r][c] &= !pair;
+                            eliminated = true;
+                        }
+                    }
+                    if eliminated {
+                        return Some(Deduction { technique: Technique::NakedPair, unit: Some(unit), placed: None });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Sudoku {
+    /// Renders the grid for ANSI terminals, empty cells as dimmed dots
+    pub fn to_ansi(&self) -> String {
+        self.render_ansi(None)
+    }
+
+    /// Renders the grid for ANSI terminals with every cell holding `digit` highlighted.
+    /// Empty cells where `digit` is still a candidate get a coloured dot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `digit` is not between 1 and 9.
+    pub fn to_ansi_highlight(&self, digit: u8) -> String {
+        assert!((1..=9).contains(&digit), "digit must be between 1 and 9, got {}", digit);
+        self.render_ansi(Some(digit))
+    }
+
+    fn render_ansi(&self, highlight: Option<u8>) -> String {
+        let mut out = String::new();
+        // writing into a String can't fail
+        let _ = self.write_ansi(&mut out, highlight);
+        out
+    }
+
+    fn write_ansi<W: std::fmt::Write>(&self, f: &mut W, highlight: Option<u8>) -> std::fmt::Result {
+        for (row, line) in self.table.iter().enumerate() {
+            if row % 3 == 0 && row != 0 {
+                writeln!(f, "{}------+-------+------{}", ANSI_DIM, ANSI_RESET)?;
+            }
+            for (col, &cell) in line.iter().enumerate() {
+                if col % 3 == 0 && col != 0 {
+                    write!(f, "{}|{} ", ANSI_DIM, ANSI_RESET)?;
+                }
+                match highlight {
+                    Some(digit) if cell == digit => write!(f, "{}{}{} ", ANSI_HIGHLIGHT, cell, ANSI_RESET)?,
+                    Some(digit) if cell == 0 && self.candidate_mask(row, col) & (1 << digit) != 0 => {
+                        write!(f, "{}.{} ", ANSI_CANDIDATE, ANSI_RESET)?
+                    }
+                    _ if cell == 0 => write!(f, "{}.{} ", ANSI_DIM, ANSI_RESET)?,
+                    _ => write!(f, "{} ", cell)?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the `Display` rendering straight to an `io::Write` sink
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "{}", self)
+    }
+
+    /// Renders the grid as text according to the given options
+    pub fn format(&self, opts: &DisplayOptions) -> String {
+        let mut out = String::new();
+        // writing into a String can't fail
+        let _ = write_grid(&mut out, &self.table, opts);
+        out
+    }
+}
+
+/// Builds a [`Sudoku`] from a grid literal, `.` or `0` marking empty cells. Whitespace and the
+/// `|`, `-` and `+` separators are ignored, so rows can be laid out like the printed grid.
+///
+/// ```
+/// let sudoku = sudoku_solver::sudoku! {
+///     5 3 . | . 7 . | . . .
+///     6 . . | 1 9 5 | . . .
+///     . 9 8 | . . . | . 6 .
+///     - - - + - - - + - - -
+///     8 . . | . 6 . | . . 3
+///     4 . . | 8 . 3 | . . 1
+///     7 . . | . 2 . | . . 6
+///     - - - + - - - + - - -
+///     . 6 . | . . . | 2 8 .
+///     . . . | 4 1 9 | . . 5
+///     . . . | . 8 . | . 7 9
+/// };
+/// assert_eq!(sudoku.get(0, 0), 5);
+/// ```
+///
+/// # Panics
+///
+/// Panics at runtime if the literal doesn't describe 81 cells.
+#[macro_export]
+macro_rules! sudoku {
+    ($($token:tt)*) => {
+        $crate::Sudoku::from_macro_literal(stringify!($($token)*))
+    };
+}
+
+impl Sudoku {
+    /// Parses the stringified tokens of the `sudoku!` macro
+    #[doc(hidden)]
+    pub fn from_macro_literal(literal: &str) -> Sudoku {
+        let cells: String = literal
+            .chars()
+            .filter(|c| !c.is_whitespace() && !matches!(c, '|' | '-' | '+'))
+            .map(|c| if c == '.' { '0' } else { c })
+            .collect();
+        match cells.parse() {
+            Ok(sudoku) => sudoku,
+            Err(SudokuError { details }) => panic!("invalid sudoku! literal: {}", details),
+        }
+    }
+
+    /// The grid as 81 row-major bytes, 0 for empty cells
+    pub fn as_bytes(&self) -> [u8; 81] {
+        std::array::from_fn(|i| self.table[i / 9][i % 9])
+    }
+
+    /// Builds a grid from 81 row-major bytes as produced by `as_bytes`
+    pub fn from_bytes(bytes: &[u8; 81]) -> Result<Sudoku, SudokuError> {
+        let mut table = [[0u8; 9]; 9];
+        for (i, &val) in bytes.iter().enumerate() {
+            let (row, col) = (i / 9, i % 9);
+            if val > 9 {
+                return Err(SudokuError{details: format!("Invalid value {} at {},{}.", val, row, col)});
+            }
+            table[row][col] = val;
+        }
+        Ok(Sudoku::from_table(table))
+    }
+
+    /// Parses a puzzle preceded by an optional title line, e.g. `Puzzle: name`, returning the
+    /// title without its `Puzzle:` prefix. Blank lines and lines starting with `#` are skipped,
+    /// the remaining lines hold the 81 cells with `0` or `.` for empty ones.
+    pub fn from_annotated(s: &str) -> Result<(Option<String>, Sudoku), SudokuError> {
+        let mut lines = trim_input(s)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .peekable();
+        let is_cells = |line: &str| line.chars().all(|c| c.is_ascii_digit() || c == '.' || c.is_whitespace());
+        let title = lines.next_if(|line| !is_cells(line)).map(|line| {
+            let line = line.strip_prefix("Puzzle:").unwrap_or(line);
+            line.trim().to_string()
+        });
+        let cells: String = lines
+            .flat_map(str::chars)
+            .filter(|c| !c.is_whitespace())
+            .map(|c| if c == '.' { '0' } else { c })
+            .collect();
+        Ok((title, cells.parse()?))
+    }
+
+    /// Parses the 9-line Simple Sudoku export layout, with `.` for empty cells, `|` between
+    /// blocks and `-`, `+` and `*` drawing the borders
+    pub fn from_simple_sudoku(s: &str) -> Result<Sudoku, SudokuError> {
+        let cells: String = trim_input(s)
+            .chars()
+            .filter(|c| !c.is_whitespace() && !matches!(c, '|' | '-' | '+' | '*'))
+            .map(|c| if c == '.' { '0' } else { c })
+            .collect();
+        cells.parse()
+    }
+
+    /// Renders the grid in the Simple Sudoku export layout
+    pub fn to_simple_sudoku(&self) -> String {
+        let mut out = String::from("*-----------*\n");
+        for (row, line) in self.table.iter().enumerate() {
+            if row % 3 == 0 && row != 0 {
+                out.push_str("|---+---+---|\n");
+            }
+            for (col, &cell) in line.iter().enumerate() {
+                if col % 3 == 0 {
+                    out.push('|');
+                }
+                out.push(if cell == 0 { '.' } else { char::from(b'0' + cell) });
+            }
+            out.push_str("|\n");
+        }
+        out.push_str("*-----------*\n");
+        out
+    }
+}
+
+/// Options controlling [`Sudoku::to_svg`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SvgOptions {
+    /// Width and height of the image in pixels
+    pub size: u32,
+    /// Whether to draw the candidates of empty cells as pencil marks
+    pub candidates: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions { size: 450, candidates: false }
+    }
+}
+
+impl Sudoku {
+    /// Renders the grid as a standalone SVG image with thick block borders, clue digits in bold
+    /// and optionally the candidates of the empty cells
+    pub fn to_svg(&self, opts: &SvgOptions) -> String {
+        let size = opts.size as f32;
+        let cell = size / 9.0;
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n",
+            opts.size
+        );
+        out.push_str(&format!("<rect width=\"{0}\" height=\"{0}\" fill=\"white\"/>\n", opts.size));
+        // the frame is inset by half its stroke so none of it is clipped by the viewBox
+        out.push_str(&format!(
+            "<rect x=\"1.5\" y=\"1.5\" width=\"{0}\" height=\"{0}\" fill=\"none\" stroke=\"black\" stroke-width=\"3\"/>\n",
+            size - 3.0
+        ));
+        for i in 1..9 {
+            let pos = i as f32 * cell;
+            let width = if i % 3 == 0 { 3 } else { 1 };
+            out.push_str(&format!(
+                "<line x1=\"{0}\" y1=\"0\" x2=\"{0}\" y2=\"{1}\" stroke=\"black\" stroke-width=\"{2}\"/>\n",
+                pos, size, width
+            ));
+            out.push_str(&format!(
+                "<line x1=\"0\" y1=\"{0}\" x2=\"{1}\" y2=\"{0}\" stroke=\"black\" stroke-width=\"{2}\"/>\n",
+                pos, size, width
+            ));
+        }
+        for row in 0..9 {
+            for col in 0..9 {
+                let (x, y) = (col as f32 * cell, row as f32 * cell);
+                let val = self.table[row][col];
+                if val != 0 {
+                    let weight = if self.givens[row][col] { "bold" } else { "normal" };
+                    out.push_str(&format!(
+                        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-weight=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                        x + cell / 2.0,
+                        y + cell / 2.0,
+                        cell * 0.6,
+                        weight,
+                        val
+                    ));
+                } else if opts.candidates {
+                    // pencil marks sit in a 3x3 layout inside the cell
+                    for d in digits(self.candidate_mask(row, col)) {
+                        let i = (d - 1) as f32;
+                        out.push_str(&format!(
+                            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" fill=\"gray\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                            x + cell * ((i % 3.0) * 2.0 + 1.0) / 6.0,
+                            y + cell * ((i / 3.0).floor() * 2.0 + 1.0) / 6.0,
+                            cell * 0.25,
+                            d
+                        ));
+                    }
+                }
+            }
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+impl FromStr for Sudoku {
+    type Err = SudokuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = trim_input(s).replace(['\r', '\n'], "");
+        if input.len() != 81 {
+            return Err(SudokuError{details: "Table needs to have 81 cells.".to_string()});
+        }
+        let mut table = [[0u8; 9]; 9];
+        for (i, c) in input.chars().enumerate() {
+            let row = i / 9;
+            let col = i % 9;
+            let val = match c.to_digit(10) {
+                Some(c) => c,
+                None => return Err(SudokuError{details: format!("Invalid character {} at {},{}.", c, row, col)}),
+            };
+            table[row][col] = val.try_into()?;
+        }
+        Ok(Sudoku::from_table(table))
+    }
+}
+
+/// Layer of pencil marks, one candidate bitset per cell, kept apart from the grid so user
+/// annotations and computed candidates can be stored and combined separately
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PencilMarks {
+    marks: [[u16; 9]; 9],
+}
+
+impl PencilMarks {
+    /// Flips a mark, returning whether it is now set
+    ///
+    /// # Panics
+    ///
+    /// Panics if `digit` is not between 1 and 9.
+    pub fn toggle(&mut self, row: usize, col: usize, digit: u8) -> bool {
+        assert!((1..=9).contains(&digit), "digit must be between 1 and 9, got {}", digit);
+        self.marks[row][col] ^= 1 << digit;
+        self.has(row, col, digit)
+    }
+
+    /// Returns true if the cell is marked with `digit`
+    pub fn has(&self, row: usize, col: usize, digit: u8) -> bool {
+        digit <= 9 && self.marks[row][col] & (1 << digit) != 0
+    }
+
+    /// Digits marked in a cell, in ascending order
+    pub fn get(&self, row: usize, col: usize) -> Vec<u8> {
+        digits(self.marks[row][col]).collect()
+    }
+
+    /// Marks set in either layer
+    pub fn union(&self, other: &PencilMarks) -> PencilMarks {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Marks set in both layers
+    pub fn intersect(&self, other: &PencilMarks) -> PencilMarks {
+        self.combine(other, |a, b| a & b)
+    }
+
+    fn combine(&self, other: &PencilMarks, op: impl Fn(u16, u16) -> u16) -> PencilMarks {
+        PencilMarks {
+            marks: std::array::from_fn(|r| std::array::from_fn(|c| op(self.marks[r][c], other.marks[r][c]))),
+        }
+    }
+}
+
+/// Jigsaw sudoku, where nine irregular regions take the place of the 3x3 blocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JigsawSudoku {
+    /// region id, from 0 to 8, of every cell
+    regions: [[u8; 9]; 9],
+    /// clues and values, with the regions as custom units replacing the blocks
+    grid: Sudoku,
+}
+
+impl JigsawSudoku {
+    /// Combines a clue grid with a region layout holding the region id (0 to 8) of every cell
+    pub fn new(grid: Sudoku, regions: [[u8; 9]; 9]) -> Result<JigsawSudoku, SudokuError> {
+        let mut units = Vec::with_capacity(9);
+        for region in 0..9u8 {
+            let cells: Vec<(usize, usize)> =
+                (0..81).map(|i| (i / 9, i % 9)).filter(|&(r, c)| regions[r][c] == region).collect();
+            let cells: [(usize, usize); 9] = cells.try_into().map_err(|cells: Vec<(usize, usize)>| SudokuError {
+                details: format!("Region {} has {} cells instead of 9.", region, cells.len()),
+            })?;
+            units.push(cells);
+        }
+        Ok(JigsawSudoku { regions, grid: grid.with_custom_regions(units) })
+    }
+
+    /// Region id of every cell
+    pub fn regions(&self) -> &[[u8; 9]; 9] {
+        &self.regions
+    }
+
+    /// The underlying grid, constrained by rows, columns and regions
+    pub fn grid(&self) -> &Sudoku {
+        &self.grid
+    }
+
+    /// Returns true if the grid is complete with every row, column and region holding 1 to 9
+    pub fn valid(&self) -> bool {
+        self.grid.valid()
+    }
+
+    /// Solves the puzzle in place, returning whether a solution was found
+    pub fn solve(&mut self) -> bool {
+        self.grid.solve()
+    }
+}
+
+/// Parses the 81 cell clue grid followed by the 81 cell region layout, separated by a blank line
+impl FromStr for JigsawSudoku {
+    type Err = SudokuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.replace("\r\n", "\n");
+        let Some((clues, layout)) = trim_input(&s).split_once("\n\n") else {
+            return Err(SudokuError{details: "Expected a clue grid and a region layout separated by a blank line.".to_string()});
+        };
+        let grid: Sudoku = clues.parse()?;
+        let layout = layout.trim().replace("\n", "");
+        if layout.len() != 81 {
+            return Err(SudokuError{details: "Region layout needs to have 81 cells.".to_string()});
+        }
+        let mut regions = [[0u8; 9]; 9];
+        for (i, c) in layout.chars().enumerate() {
+            let (row, col) = (i / 9, i % 9);
+            regions[row][col] = match c.to_digit(9) {
+                Some(id) => id.try_into()?,
+                None => return Err(SudokuError{details: format!("Invalid region {} at {},{}.", c, row, col)}),
+            };
+        }
+        JigsawSudoku::new(grid, regions)
+    }
+}
+
+/// Builds a grid from `(row, col, value)` clue triples, starting from an empty grid.
+/// Later triples overwrite earlier ones for the same cell.
+///
+/// # Panics
+///
+/// Panics if a coordinate is outside the 9x9 grid or a value is greater than 9.
+impl FromIterator<(usize, usize, u8)> for Sudoku {
+    fn from_iter<T: IntoIterator<Item = (usize, usize, u8)>>(iter: T) -> Self {
+        let mut table = [[0u8; 9]; 9];
+        for (row, col, val) in iter {
+            assert!(row < 9 && col < 9, "cell {},{} is outside the grid", row, col);
+            assert!(val <= 9, "invalid value {} at {},{}", val, row, col);
+            table[row][col] = val;
+        }
+        Sudoku::from_table(table)
+    }
+}
+
+impl Display for Sudoku {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_grid(f, &self.table, &DisplayOptions::default())
+    }
+}
+
+/// Characters used to draw the lines between blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    /// `|`, `-` and `+`
+    Ascii,
+    /// Box drawing characters `│`, `─` and `┼`
+    Unicode,
+}
+
+/// Options controlling the text rendering of [`Sudoku::format`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Character shown for empty cells
+    pub empty: char,
+    /// Whether to draw lines between blocks
+    pub block_separators: bool,
+    /// Characters used for the block separators
+    pub borders: BorderStyle,
+}
+
+/// Matches the `Display` output: `0` for empty cells and ASCII separators
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            empty: '0',
+            block_separators: true,
+            borders: BorderStyle::Ascii,
+        }
+    }
+}
+
+/// Writes a square grid of any size, padding every cell to the width of the
+/// largest value so columns stay aligned (e.g. 9x9 uses one digit, 16x16 two)
+fn write_grid<W: std::fmt::Write, R: AsRef<[u8]>>(f: &mut W, rows: &[R], opts: &DisplayOptions) -> std::fmt::Result {
+    let (vertical, horizontal, cross) = match opts.borders {
+        BorderStyle::Ascii => ('|', '-', '+'),
+        BorderStyle::Unicode => ('│', '─', '┼'),
+    };
+    let size = rows.len();
+    let block = (1..=size).find(|b| b * b >= size).unwrap_or(1);
+    let width = size.checked_ilog10().map_or(1, |digits| digits as usize + 1);
+    let block_width = block * (width + 1);
+    let blocks = size.div_ceil(block);
+    for (i, row) in rows.iter().enumerate() {
+        if opts.block_separators && i % block == 0 && i != 0 {
+            for k in 0..blocks {
+                if k != 0 {
+                    f.write_char(cross)?;
+                }
+                // inner segments also cover the space after a `|`, the last one drops the trailing space
+                let dashes = block_width + usize::from(k != 0) - usize::from(k == blocks - 1);
+                for _ in 0..dashes {
+                    f.write_char(horizontal)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        for (j, &cell) in row.as_ref().iter().enumerate() {
+            if opts.block_separators && j % block == 0 && j != 0 {
+                write!(f, "{} ", vertical)?;
+            }
+            if cell == 0 {
+                write!(f, "{:>width$} ", opts.empty, width = width)?;
+            } else {
+                write!(f, "{:>width$} ", cell, width = width)?;
+            }
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Puzzle solvable with singles only
+    const EASY_PUZZLE: &str = "530070000\n\
+        600195000\n\
+        098000060\n\
+        800060003\n\
+        400803001\n\
+        700020006\n\
+        060000280\n\
+        000419005\n\
+        000080079";
+
+    /// Puzzle which can't be solved without guessing
+    const HARD_PUZZLE: &str = "100007090\n\
+        030020008\n\
+        009600500\n\
+        005300900\n\
+        010080002\n\
+        600004000\n\
+        300000010\n\
+        040000007\n\
+        007000300";
+
+    /// Solved EASY_PUZZLE with the rectangle of swappable 6s and 7s at (0,3), (0,4), (3,3) and
+    /// (3,4) emptied, leaving exactly two solutions
+    fn two_solution_grid() -> Sudoku {
+        let mut sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        sudoku.solve();
+        for (row, col) in [(0, 3), (0, 4), (3, 3), (3, 4)] {
+            sudoku.clear(row, col);
+        }
+        sudoku
+    }
+
+    /// Consistent grid without a solution: row 0 holds 1-8 and the column of its empty cell
+    /// already has the 9 it needs
+    fn dead_cell_grid() -> Sudoku {
+        let mut table = [[0; 9]; 9];
+        table[0] = [1, 2, 3, 4, 5, 6, 7, 8, 0];
+        table[5][8] = 9;
+        Sudoku::from_table(table)
+    }
+
+    #[test]
+    fn test_validate_sudoku() {
+        let sudoku: Sudoku = "534678912\n\
+             672195348\n\
+             198342567\n\
+             859761423\n\
+             426853791\n\
+             713924856\n\
+             961537284\n\
+             287419635\n\
+             345286179"
+            .parse()
+            .unwrap();
+        println!("{}", sudoku);
+        assert!(sudoku.valid());
+    }
+
+    #[test]
+    fn test_invalid_sudoku() {
+        let sudoku: Sudoku = "534678912\n\
+             672195348\n\
+             198342567\n\
+             859761423\n\
+             426853791\n\
+             713924856\n\
+             961537284\n\
+             287419635\n\
+             345286177"
+            .parse()
+            .unwrap();
+        println!("{}", sudoku);
+        assert!(!sudoku.valid());
+    }
+
+    #[test]
+    fn test_invalid_3x3_blocks() {
+        let sudoku: Sudoku = "123456789\n\
+            234567891\n\
+            345678912\n\
+            456789123\n\
+            567891234\n\
+            678912345\n\
+            789123456\n\
+            891234567\n\
+            912345678"
+            .parse()
+            .unwrap();
+        println!("{}", sudoku);
+        assert!(!sudoku.valid());
+    }
+
+    #[test]
+    fn test_invalid_cell() {
+        let sudoku: Sudoku = "123456789\n\
+            456789123\n\
+            789123456\n\
+            214365978\n\
+            365897214\n\
+            897214365\n\
+            531642978\n\
+            642978531\n\
+            978531640"
+            .parse()
+            .unwrap();
+        println!("{}", sudoku);
+        assert!(!sudoku.valid());
+    }
+
+    #[test]
+    fn test_display_9x9() {
+        let sudoku: Sudoku = "534678912\n\
+             672195348\n\
+             198342567\n\
+             859761423\n\
+             426853791\n\
+             713924856\n\
+             961537284\n\
+             287419635\n\
+             345286179"
+            .parse()
+            .unwrap();
+        let out = sudoku.to_string();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "5 3 4 | 6 7 8 | 9 1 2 ");
+        assert_eq!(lines[3], "------+-------+------");
+    }
+
+    #[test]
+    fn test_display_16x16_alignment() {
+        let rows: Vec<Vec<u8>> = (0..16)
+            .map(|r| (0..16).map(|c| ((r * 4 + r / 4 + c) % 16 + 1) as u8).collect())
+            .collect();
+        let mut out = String::new();
+        write_grid(&mut out, &rows, &DisplayOptions::default()).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 19);
+        let bars: Vec<usize> = lines[0].match_indices('|').map(|(i, _)| i).collect();
+        assert_eq!(bars.len(), 3);
+        for line in &lines {
+            assert_eq!(line.trim_end().len(), lines[0].trim_end().len());
+            let sep = if line.starts_with('-') { '+' } else { '|' };
+            let marks: Vec<usize> = line.match_indices(sep).map(|(i, _)| i).collect();
+            assert_eq!(marks, bars);
+        }
+        assert!(lines[0].starts_with(" 1  2  3  4 |  5 "));
+    }
+
+    #[test]
+    fn test_peer_same_row() {
+        assert!(Sudoku::is_peer((4, 0), (4, 8)));
+        assert!(Sudoku::is_peer((0, 2), (7, 2)));
+        assert!(!Sudoku::is_peer((4, 4), (4, 4)));
+    }
+
+    #[test]
+    fn test_peer_same_block() {
+        assert!(Sudoku::is_peer((3, 3), (5, 5)));
+        assert!(Sudoku::is_peer((6, 8), (8, 6)));
+    }
+
+    #[test]
+    fn test_peer_unrelated() {
+        assert!(!Sudoku::is_peer((0, 0), (4, 4)));
+        assert!(!Sudoku::is_peer((2, 3), (3, 2)));
+    }
+
+    #[test]
+    fn test_solve_strategies() {
+        let puzzle: Sudoku = EASY_PUZZLE.parse().unwrap();
+        for strategy in [Strategy::Backtrack, Strategy::ConstraintPropagation, Strategy::HumanTechniquesOnly] {
+            let mut sudoku = puzzle.clone();
+            assert!(sudoku.solve_with(strategy));
+            assert!(sudoku.valid());
+            assert_eq!(sudoku.table[8], [3, 4, 5, 2, 8, 6, 1, 7, 9]);
+        }
+    }
+
+    #[test]
+    fn test_human_techniques_need_guess() {
+        let puzzle: Sudoku = HARD_PUZZLE.parse().unwrap();
+        let mut sudoku = puzzle.clone();
+        assert!(!sudoku.solve_with(Strategy::HumanTechniquesOnly));
+        assert_eq!(sudoku, puzzle);
+        assert!(sudoku.solve_with(Strategy::Backtrack));
+        assert!(sudoku.valid());
+        let mut other = puzzle.clone();
+        assert!(other.solve_with(Strategy::ConstraintPropagation));
+        assert_eq!(other, sudoku);
+    }
+
+    #[test]
+    fn test_solve_conflicting_givens() {
+        let mut sudoku: Sudoku = "550070000\n\
+             600195000\n\
+             098000060\n\
+             800060003\n\
+             400803001\n\
+             700020006\n\
+             060000280\n\
+             000419005\n\
+             000080079"
+            .parse()
+            .unwrap();
+        assert!(!sudoku.solve_with(Strategy::Backtrack));
+        assert!(!sudoku.solve());
+    }
+
+    #[test]
+    fn test_clue_distribution() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let (rows, cols, blocks) = sudoku.clue_distribution();
+        assert_eq!(rows, [3, 4, 3, 3, 4, 3, 3, 4, 3]);
+        assert_eq!(cols, [5, 3, 1, 3, 6, 3, 1, 3, 5]);
+        assert_eq!(blocks, [5, 4, 1, 3, 4, 3, 1, 4, 5]);
+        for counts in [rows, cols, blocks] {
+            assert_eq!(counts.iter().map(|&c| c as usize).sum::<usize>(), sudoku.clue_count());
+        }
+        assert_eq!(sudoku.clue_count(), 30);
+    }
+
+    #[test]
+    fn test_autofill_candidates() {
+        let sudoku: Sudoku = HARD_PUZZLE.parse().unwrap();
+        let bulk = sudoku.autofill_candidates();
+        for (row, line) in bulk.iter().enumerate() {
+            for (col, cands) in line.iter().enumerate() {
+                assert_eq!(*cands, sudoku.candidates(row, col));
+            }
+        }
+        assert_eq!(sudoku.candidates(0, 1), vec![2, 5, 6, 8]);
+        assert!(bulk[0][0].is_empty());
+    }
+
+    #[test]
+    fn test_collect_triples() {
+        let sudoku: Sudoku = vec![(0, 0, 5), (0, 1, 3), (4, 4, 5), (8, 8, 9)].into_iter().collect();
+        let expected: Sudoku = "530000000\n\
+             000000000\n\
+             000000000\n\
+             000000000\n\
+             000050000\n\
+             000000000\n\
+             000000000\n\
+             000000000\n\
+             000000009"
+            .parse()
+            .unwrap();
+        assert_eq!(sudoku, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_collect_out_of_range() {
+        let _: Sudoku = vec![(9, 0, 1)].into_iter().collect();
+    }
+
+    #[test]
+    fn test_generate_with_clues() {
+        for seed in [1, 42] {
+            let sudoku = Sudoku::generate_with_clues(28, seed).unwrap();
+            assert_eq!(sudoku.clue_count(), 28);
+            assert!(sudoku.has_unique_solution());
+        }
+        assert!(Sudoku::generate_with_clues(10, 1).is_none());
+    }
+
+    #[test]
+    fn test_sdm_round_trip() {
+        let mut solved: Sudoku = EASY_PUZZLE.parse().unwrap();
+        solved.solve();
+        let puzzles: Vec<Sudoku> = vec![EASY_PUZZLE.parse().unwrap(), HARD_PUZZLE.parse().unwrap(), solved];
+        let mut buf = Vec::new();
+        Sudoku::write_sdm(&puzzles, &mut buf).unwrap();
+        let text = String::from_utf8(buf.clone()).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.starts_with("530070000600195000"));
+        let read = Sudoku::read_sdm(&buf[..]).unwrap();
+        assert_eq!(read, puzzles);
+    }
+
+    #[test]
+    fn test_sdm_invalid_line() {
+        let input = "530070000600195000098000060800060003400803001700020006060000280000419005000080079\n\nabc\n";
+        let err = Sudoku::read_sdm(input.as_bytes()).unwrap_err();
+        assert!(err.details.starts_with("Line 3"));
+    }
+
+    #[test]
+    fn test_ansi_highlight() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let plain = sudoku.to_ansi();
+        assert!(!plain.contains(ANSI_HIGHLIGHT));
+        let out = sudoku.to_ansi_highlight(5);
+        let marked = format!("{}5{}", ANSI_HIGHLIGHT, ANSI_RESET);
+        let fives = sudoku.table.iter().flatten().filter(|&&v| v == 5).count();
+        assert_eq!(out.matches(&marked).count(), fives);
+        assert_eq!(out.matches(ANSI_HIGHLIGHT).count(), fives);
+        assert!(out.lines().next().unwrap().starts_with(&marked));
+        assert_eq!(out.lines().count(), 11);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ansi_highlight_invalid_digit() {
+        Sudoku::default().to_ansi_highlight(0);
+    }
+
+    #[test]
+    fn test_remove_clue_uniqueness() {
+        let mut sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        sudoku.solve();
+        // (0,3), (0,4), (3,3) and (3,4) form a rectangle of swappable 6s and 7s
+        assert!(sudoku.remove_clue(0, 3));
+        assert!(sudoku.remove_clue(0, 4));
+        assert!(sudoku.remove_clue(3, 3));
+        assert_eq!(sudoku.clue_count(), 78);
+        assert!(!sudoku.remove_clue(3, 4));
+        assert_eq!(sudoku.clue_count(), 77);
+        sudoku.clear(3, 4);
+        assert_eq!(sudoku, two_solution_grid());
+        assert_eq!(sudoku.solution_count(3), 2);
+    }
+
+    #[test]
+    fn test_complexity_profile() {
+        let easy: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let profile = easy.complexity_profile();
+        assert!(profile.keys().all(|t| matches!(t, Technique::NakedSingle | Technique::HiddenSingle)));
+        assert_eq!(profile.values().sum::<usize>(), 81 - easy.clue_count());
+
+        let harder: Sudoku = "300500100\n\
+             000840000\n\
+             002001605\n\
+             185030000\n\
+             000005030\n\
+             090000000\n\
+             400000007\n\
+             030786200\n\
+             000000008"
+            .parse()
+            .unwrap();
+        let profile = harder.complexity_profile();
+        assert!(profile[&Technique::PointingPair] > 0);
+        assert!(harder.clone().solve_with(Strategy::HumanTechniquesOnly));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let initial: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let mut sudoku = initial.clone();
+        assert!(!sudoku.undo());
+        assert!(sudoku.set(0, 0, 1).is_err());
+        sudoku.set(0, 2, 4).unwrap();
+        sudoku.set(0, 3, 6).unwrap();
+        assert_eq!(sudoku.get(0, 2), 4);
+        assert!(sudoku.undo());
+        assert!(sudoku.undo());
+        assert!(!sudoku.undo());
+        assert_eq!(sudoku, initial);
+        assert!(sudoku.redo());
+        assert_eq!(sudoku.get(0, 2), 4);
+        assert_eq!(sudoku.get(0, 3), 0);
+        sudoku.set(1, 1, 7).unwrap();
+        assert!(!sudoku.redo());
+        assert!(sudoku.is_given(0, 0));
+        assert!(!sudoku.is_given(0, 2));
+    }
+
+    #[test]
+    fn test_validate_givens() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert!(sudoku.validate_givens().is_ok());
+        let sudoku: Sudoku = "530070500\n\
+             600195000\n\
+             098000060\n\
+             800060003\n\
+             400803001\n\
+             700020006\n\
+             060000280\n\
+             000419005\n\
+             000080079"
+            .parse()
+            .unwrap();
+        let err = sudoku.validate_givens().unwrap_err();
+        assert_eq!(err.details, "Digit 5 appears twice in row 1 at 0,0 and 0,6.");
+    }
+
+    #[test]
+    fn test_format_options() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert_eq!(sudoku.format(&DisplayOptions::default()), sudoku.to_string());
+
+        let dots = DisplayOptions { empty: '.', block_separators: false, ..Default::default() };
+        let out = sudoku.format(&dots);
+        assert_eq!(out.lines().count(), 9);
+        assert_eq!(out.lines().next().unwrap(), "5 3 . . 7 . . . . ");
+
+        let unicode = DisplayOptions { empty: ' ', borders: BorderStyle::Unicode, ..Default::default() };
+        let out = sudoku.format(&unicode);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "5 3   │   7   │       ");
+        assert_eq!(lines[3], "──────┼───────┼──────");
+    }
+
+    #[test]
+    fn test_isomorphic() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let transpose = Sudoku::from_table(transposed(&sudoku.table));
+        assert!(sudoku.is_isomorphic_to(&transpose));
+        assert!(transpose.is_isomorphic_to(&sudoku));
+
+        // swap the first two bands and relabel 1 <-> 2
+        let mut table = sudoku.table;
+        table.rotate_left(3);
+        for val in table.iter_mut().flatten() {
+            *val = match *val {
+                1 => 2,
+                2 => 1,
+                v => v,
+            };
+        }
+        assert!(sudoku.is_isomorphic_to(&Sudoku::from_table(table)));
+
+        let unrelated = Sudoku::generate_with_clues(30, 7).unwrap();
+        assert!(!sudoku.is_isomorphic_to(&unrelated));
+        assert!(!sudoku.is_isomorphic_to(&HARD_PUZZLE.parse().unwrap()));
+    }
+
+    #[test]
+    fn test_budget_partial_progress() {
+        let puzzle: Sudoku = HARD_PUZZLE.parse().unwrap();
+        let mut solution = puzzle.clone();
+        assert_eq!(solution.solve_with_budget(1000), SolveOutcome::Solved);
+        assert!(solution.valid());
+
+        let mut sudoku = puzzle.clone();
+        assert_eq!(sudoku.solve_with_budget(0), SolveOutcome::BudgetExceeded);
+        assert!(sudoku.clue_count() > puzzle.clue_count());
+        assert!(sudoku.consistent());
+        for row in 0..9 {
+            for col in 0..9 {
+                let val = sudoku.get(row, col);
+                assert!(val == 0 || val == solution.get(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn test_most_constrained() {
+        let sudoku: Sudoku = HARD_PUZZLE.parse().unwrap();
+        let (row, col) = sudoku.most_constrained_cell().unwrap();
+        let min = sudoku
+            .autofill_candidates()
+            .iter()
+            .flatten()
+            .filter(|c| !c.is_empty())
+            .map(|c| c.len())
+            .min()
+            .unwrap();
+        assert_eq!(sudoku.get(row, col), 0);
+        assert_eq!(sudoku.candidates(row, col).len(), min);
+
+        assert_eq!(sudoku.empties_in_unit(Unit::Row(0)), 6);
+        assert_eq!(sudoku.empties_in_unit(Unit::Column(0)), 6);
+        assert_eq!(sudoku.empties_in_unit(Unit::Block(4)), 6);
+        let unit = sudoku.most_constrained_unit().unwrap();
+        assert!((0..9).all(|i| sudoku.empties_in_unit(Unit::Row(i)) >= sudoku.empties_in_unit(unit)));
+
+        let mut solved = sudoku.clone();
+        solved.solve();
+        assert_eq!(solved.most_constrained_cell(), None);
+        assert_eq!(solved.most_constrained_unit(), None);
+    }
+
+    #[test]
+    fn test_macro_complete() {
+        let sudoku = sudoku! {
+            5 3 4 | 6 7 8 | 9 1 2
+            6 7 2 | 1 9 5 | 3 4 8
+            1 9 8 | 3 4 2 | 5 6 7
+            ------+-------+------
+            8 5 9 | 7 6 1 | 4 2 3
+            4 2 6 | 8 5 3 | 7 9 1
+            7 1 3 | 9 2 4 | 8 5 6
+            ------+-------+------
+            9 6 1 | 5 3 7 | 2 8 4
+            2 8 7 | 4 1 9 | 6 3 5
+            3 4 5 | 2 8 6 | 1 7 9
+        };
+        assert!(sudoku.valid());
+    }
+
+    #[test]
+    fn test_macro_partial() {
+        let sudoku = sudoku! {
+            5 3 . . 7 . . . .
+            6 . . 1 9 5 . . .
+            . 9 8 . . . . 6 .
+            8 . . . 6 . . . 3
+            4 . . 8 . 3 . . 1
+            7 . . . 2 . . . 6
+            . 6 . . . . 2 8 .
+            . . . 4 1 9 . . 5
+            . . . . 8 . . 7 9
+        };
+        let expected: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert_eq!(sudoku, expected);
+        assert!(sudoku.is_given(0, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_macro_malformed() {
+        sudoku! { 1 2 3 };
+    }
+
+    #[test]
+    fn test_solution_count_two() {
+        let sudoku = two_solution_grid();
+        assert_eq!(sudoku.solution_count(10), 2);
+        let solutions = sudoku.solutions(10);
+        assert_eq!(solutions.len(), 2);
+        assert_ne!(solutions[0], solutions[1]);
+        assert!(solutions.iter().all(|s| s.valid()));
+        assert_eq!(sudoku.solution_count(1), 1);
+        assert_eq!(sudoku.solution_count(0), 0);
+        assert!(sudoku.solutions(0).is_empty());
+        assert!(sudoku.enumerate(0).is_empty());
+    }
+
+    #[test]
+    fn test_solution_count_nearly_empty() {
+        let sudoku: Sudoku = vec![(0, 0, 1), (4, 4, 5), (8, 8, 9)].into_iter().collect();
+        assert_eq!(sudoku.solution_count(500), 500);
+        let mut conflicting = sudoku.clone();
+        conflicting.table[0][8] = 1;
+        assert_eq!(conflicting.solution_count(500), 0);
+    }
+
+    #[test]
+    fn test_parity_constraint() {
+        let mut sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        sudoku.set_parity(0, 2, Parity::Even);
+        sudoku.set_parity(0, 3, Parity::Even);
+        assert_eq!(sudoku.parity(0, 2), Some(Parity::Even));
+        assert!(sudoku.candidates(0, 2).iter().all(|v| v % 2 == 0));
+        assert_eq!(sudoku.autofill_candidates()[0][3], sudoku.candidates(0, 3));
+        for strategy in [Strategy::Backtrack, Strategy::ConstraintPropagation, Strategy::HumanTechniquesOnly] {
+            let mut solved = sudoku.clone();
+            assert!(solved.solve_with(strategy));
+            assert_eq!(solved.get(0, 2) % 2, 0);
+            assert_eq!(solved.get(0, 3) % 2, 0);
+        }
+
+        // the unique solution has a 4 there, so an odd constraint can't be met
+        let mut odd: Sudoku = EASY_PUZZLE.parse().unwrap();
+        odd.set_parity(0, 2, Parity::Odd);
+        assert!(odd.candidates(0, 2).iter().all(|v| v % 2 == 1));
+        assert_eq!(odd.solution_count(2), 0);
+        let mut solved: Sudoku = EASY_PUZZLE.parse().unwrap();
+        solved.solve();
+        assert!(solved.valid());
+        solved.set_parity(0, 2, Parity::Odd);
+        assert!(!solved.valid());
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let mut sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert_eq!(sudoku.check_invariants(), Ok(()));
+        sudoku.solve();
+        assert_eq!(sudoku.check_invariants(), Ok(()));
+
+        let mut duplicate = sudoku.clone();
+        duplicate.table[0][0] = duplicate.table[0][1];
+        assert!(duplicate.check_invariants().is_err());
+
+        let mut out_of_range = sudoku.clone();
+        out_of_range.table[4][4] = 12;
+        assert!(out_of_range.check_invariants().is_err());
+        // too big to be used as a candidate bit
+        out_of_range.table[4][4] = 16;
+        assert_eq!(out_of_range.check_invariants(), Err("Cell 4,4 holds invalid value 16.".to_string()));
+        out_of_range.table[4][4] = u8::MAX;
+        assert!(out_of_range.check_invariants().is_err());
+
+        let mut empty_given: Sudoku = EASY_PUZZLE.parse().unwrap();
+        empty_given.table[0][0] = 0;
+        assert_eq!(empty_given.check_invariants(), Err("Given cell 0,0 is empty.".to_string()));
+    }
+
+    #[test]
+    fn test_rate() {
+        let easy: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert_eq!(easy.rate(), Difficulty::Easy);
+        let hard: Sudoku = HARD_PUZZLE.parse().unwrap();
+        assert_eq!(hard.rate(), Difficulty::Expert);
+    }
+
+    #[test]
+    fn test_generate_easy() {
+        for seed in 0..3 {
+            let generated = Sudoku::generate(Difficulty::Easy, seed);
+            assert_eq!(generated.rating, Difficulty::Easy);
+            assert_eq!(generated.puzzle.rate(), Difficulty::Easy);
+            assert!(generated.puzzle.has_unique_solution());
+            assert!(generated.attempts >= 1);
+            assert!(EASY_CLUES.contains(&generated.puzzle.clue_count()));
+        }
+        let bands = [
+            (Difficulty::Easy, EASY_CLUES),
+            (Difficulty::Medium, MEDIUM_CLUES),
+            (Difficulty::Hard, HARD_CLUES),
+            (Difficulty::Expert, EXPERT_CLUES),
+        ];
+        for (difficulty, clues) in bands {
+            for seed in 0..3 {
+                let generated = Sudoku::generate(difficulty, seed);
+                assert!(clues.contains(&generated.puzzle.clue_count()), "{:?} seed {}", difficulty, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_simple_sudoku_round_trip() {
+        let export = "*-----------*\n\
+            |53.|.7.|...|\n\
+            |6..|195|...|\n\
+            |.98|...|.6.|\n\
+            |---+---+---|\n\
+            |8..|.6.|..3|\n\
+            |4..|8.3|..1|\n\
+            |7..|.2.|..6|\n\
+            |---+---+---|\n\
+            |.6.|...|28.|\n\
+            |...|419|..5|\n\
+            |...|.8.|.79|\n\
+            *-----------*\n";
+        let sudoku = Sudoku::from_simple_sudoku(export).unwrap();
+        assert_eq!(sudoku, EASY_PUZZLE.parse().unwrap());
+        assert_eq!(sudoku.to_simple_sudoku(), export);
+        assert!(Sudoku::from_simple_sudoku("|53.|").is_err());
+    }
+
+    #[test]
+    fn test_cells_matching() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert_eq!(sudoku.cells_matching(|_, _, v| v == 9), vec![(1, 4), (2, 1), (7, 5), (8, 8)]);
+        assert_eq!(sudoku.cells_matching(|_, _, v| v == 0).len(), 51);
+        assert_eq!(sudoku.cells_matching(|row, _, v| row == 0 && v != 0), vec![(0, 0), (0, 1), (0, 4)]);
+    }
+
+    #[test]
+    fn test_hint_prefers_naked_single() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        // the grid offers hidden singles as well as naked singles
+        let hidden = sudoku.units().iter().any(|(_, cells)| {
+            (1..=9u8).any(|val| {
+                let spots: Vec<_> = cells.iter().filter(|&&(r, c)| sudoku.candidates(r, c).contains(&val)).collect();
+                spots.len() == 1 && sudoku.candidates(spots[0].0, spots[0].1).len() > 1
+            })
+        });
+        assert!(hidden);
+        let hint = sudoku.next_hint().unwrap();
+        assert_eq!(hint.technique, Some(Technique::NakedSingle));
+        assert_eq!(sudoku.candidates(hint.row, hint.col), vec![hint.value]);
+        assert!(hint.explanation.contains("naked single"));
+        assert!(hint.explanation.starts_with(&format!("R{}C{} = ", hint.row + 1, hint.col + 1)));
+        let mut solved = sudoku.clone();
+        solved.solve();
+        assert_eq!(solved.get(hint.row, hint.col), hint.value);
+    }
+
+    #[test]
+    fn test_hint_suggests_guess() {
+        let mut sudoku = two_solution_grid();
+        let hint = sudoku.next_hint().unwrap();
+        assert_eq!(hint.technique, None);
+        assert_eq!((hint.row, hint.col), (0, 3));
+        assert!(hint.explanation.contains("guess"));
+        sudoku.solve();
+        assert_eq!(sudoku.next_hint(), None);
+    }
+
+    #[test]
+    fn test_enumerate_three_completions() {
+        let sudoku: Sudoku = "004608000\n\
+             602190048\n\
+             198042567\n\
+             859701420\n\
+             420803091\n\
+             703000806\n\
+             961007280\n\
+             287010600\n\
+             305280179"
+            .parse()
+            .unwrap();
+        let all = sudoku.enumerate(10);
+        assert_eq!(all.len(), 3);
+        assert!(all.iter().all(|s| s.is_solved()));
+        assert!(all.windows(2).all(|w| w[0].table < w[1].table));
+        assert_eq!(sudoku.solution_count(10), 3);
+        assert_eq!(sudoku.enumerate(2), all[..2].to_vec());
+        assert!(!sudoku.is_complete());
+    }
+
+    /// Jigsaw layout with three irregular regions per band, as region ids per cell
+    const JIGSAW_REGIONS: &str = "000011122\n\
+        000111222\n\
+        001112222\n\
+        333344455\n\
+        333444555\n\
+        334445555\n\
+        666677788\n\
+        666777888\n\
+        667778888";
+
+    fn jigsaw_units() -> Vec<[(usize, usize); 9]> {
+        let ids: Vec<usize> = JIGSAW_REGIONS.chars().filter_map(|c| c.to_digit(10)).map(|d| d as usize).collect();
+        (0..9)
+            .map(|region| {
+                let cells: Vec<(usize, usize)> = (0..81).filter(|&i| ids[i] == region).map(|i| (i / 9, i % 9)).collect();
+                cells.try_into().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_custom_regions() {
+        let mut sudoku = Sudoku::default().with_custom_regions(jigsaw_units());
+        assert!(sudoku.solve());
+        assert!(sudoku.valid());
+        assert_eq!(sudoku.check_invariants(), Ok(()));
+        for (unit, cells) in sudoku.units() {
+            let mut vals: Vec<u8> = cells.iter().map(|&(r, c)| sudoku.get(r, c)).collect();
+            vals.sort();
+            assert_eq!(vals, (1..=9).collect::<Vec<u8>>(), "{}", unit);
+        }
+        assert_eq!(sudoku.unit_cells(Unit::Custom(0))[4], (1, 0));
+
+        // the same values checked against the standard blocks break the block rule
+        let plain = Sudoku::from_table(sudoku.table);
+        assert!(!plain.valid());
+    }
+
+    #[test]
+    fn test_custom_units_in_addition() {
+        let diagonal: [(usize, usize); 9] = std::array::from_fn(|i| (i, i));
+        let mut sudoku = Sudoku::default().with_custom_units(vec![diagonal]);
+        assert!(sudoku.solve());
+        assert!(sudoku.valid());
+        let mut vals: Vec<u8> = (0..9).map(|i| sudoku.get(i, i)).collect();
+        vals.sort();
+        assert_eq!(vals, (1..=9).collect::<Vec<u8>>());
+        assert!(Sudoku::from_table(sudoku.table).valid());
+    }
+
+    /// Jigsaw puzzle on the `JIGSAW_REGIONS` layout
+    const JIGSAW_PUZZLE: &str = "091405200\n\
+        200006000\n\
+        000001000\n\
+        002000001\n\
+        006004000\n\
+        300000004\n\
+        000900067\n\
+        010040000\n\
+        000000000";
+
+    #[test]
+    fn test_jigsaw_solve() {
+        let input = format!("{}\n\n{}", JIGSAW_PUZZLE, JIGSAW_REGIONS);
+        let mut jigsaw: JigsawSudoku = input.parse().unwrap();
+        assert_eq!(jigsaw.regions()[2][2], 1);
+        assert!(!jigsaw.valid());
+        assert!(jigsaw.grid().has_unique_solution());
+        assert!(jigsaw.solve());
+        assert!(jigsaw.valid());
+        let solution: Sudoku = "791485236\n\
+             238196745\n\
+             564731892\n\
+             942567381\n\
+             186254973\n\
+             379812654\n\
+             453928167\n\
+             617349528\n\
+             825673419"
+            .parse()
+            .unwrap();
+        assert_eq!(*jigsaw.grid(), solution);
+        // the solution breaks the standard block rule
+        assert!(!solution.valid());
+    }
+
+    #[test]
+    fn test_jigsaw_region_violation() {
+        // a second 5 in region 1, fine for rows, columns and standard blocks
+        let clues = JIGSAW_PUZZLE.replacen("000001000", "005001000", 1);
+        let standard: Sudoku = clues.parse().unwrap();
+        assert!(standard.validate_givens().is_ok());
+        let mut jigsaw: JigsawSudoku = format!("{}\n\n{}", clues, JIGSAW_REGIONS).parse().unwrap();
+        assert!(jigsaw.grid().validate_givens().is_err());
+        assert!(!jigsaw.solve());
+
+        let bad_layout = JIGSAW_REGIONS.replacen('0', "1", 1);
+        assert!(format!("{}\n\n{}", JIGSAW_PUZZLE, bad_layout).parse::<JigsawSudoku>().is_err());
+    }
+
+    #[test]
+    fn test_is_proper() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert!(sudoku.is_proper());
+
+        let mut conflicting = sudoku.clone();
+        conflicting.table[0][2] = 5;
+        assert!(!conflicting.is_proper());
+
+        let ambiguous = two_solution_grid();
+        assert!(ambiguous.clue_count() >= 17 && ambiguous.consistent());
+        assert!(!ambiguous.is_proper());
+
+        // parity constraints on every cell make 14 clues enough for a unique solution
+        let mut solution = sudoku.clone();
+        solution.solve();
+        let mut sparse: Sudoku = "000000000\n\
+             000000300\n\
+             000002000\n\
+             000000000\n\
+             000000001\n\
+             010000850\n\
+             000007004\n\
+             000000600\n\
+             005280079"
+            .parse()
+            .unwrap();
+        for row in 0..9 {
+            for col in 0..9 {
+                let parity = if solution.get(row, col).is_multiple_of(2) { Parity::Even } else { Parity::Odd };
+                sparse.set_parity(row, col, parity);
+            }
+        }
+        assert!(sparse.has_unique_solution());
+        assert!(!sparse.is_proper());
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let svg = sudoku.to_svg(&SvgOptions::default());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<text").count(), sudoku.clue_count());
+        assert_eq!(svg.matches("<line").count(), 16);
+        assert_eq!(svg.matches("stroke-width=\"3\"").count(), 5);
+        assert!(svg.contains("<rect x=\"1.5\" y=\"1.5\" width=\"447\" height=\"447\" fill=\"none\""));
+
+        let with_marks = sudoku.to_svg(&SvgOptions { size: 300, candidates: true });
+        let marks: usize = sudoku.autofill_candidates().iter().flatten().map(|c| c.len()).sum();
+        assert_eq!(with_marks.matches("<text").count(), sudoku.clue_count() + marks);
+        assert!(with_marks.contains("width=\"300\""));
+    }
+
+    #[test]
+    fn test_solution_keeps_clue_mask() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let solution = sudoku.solution().unwrap();
+        assert!(solution.is_solved());
+        assert_eq!(sudoku.clue_count(), 30);
+        for row in 0..9 {
+            for col in 0..9 {
+                assert_eq!(sudoku.is_given(row, col), sudoku.get(row, col) != 0);
+                assert_eq!(solution.is_given(row, col), sudoku.is_given(row, col));
+            }
+        }
+        assert!(!sudoku.is_complete());
+        let mut conflicting = sudoku.clone();
+        conflicting.table[0][2] = 5;
+        assert_eq!(conflicting.solution(), None);
+    }
+
+    #[test]
+    fn test_pencil_marks_toggle() {
+        let mut marks = PencilMarks::default();
+        assert!(marks.toggle(0, 2, 4));
+        assert!(marks.toggle(0, 2, 1));
+        assert_eq!(marks.get(0, 2), vec![1, 4]);
+        assert!(!marks.toggle(0, 2, 4));
+        assert!(!marks.has(0, 2, 4));
+        assert!(marks.has(0, 2, 1));
+        assert!(marks.get(1, 1).is_empty());
+    }
+
+    #[test]
+    fn test_pencil_marks_against_computed() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let computed = sudoku.computed_marks();
+        assert_eq!(computed.get(0, 2), sudoku.candidates(0, 2));
+
+        let mut user = PencilMarks::default();
+        user.toggle(0, 2, 1);
+        user.toggle(0, 2, 4);
+        user.toggle(0, 0, 7);
+        let both = user.intersect(&computed);
+        assert_eq!(both.get(0, 2), vec![1, 4]);
+        // the cell is filled, so nothing survives there
+        assert!(both.get(0, 0).is_empty());
+        assert_eq!(user.union(&computed).get(0, 0), vec![7]);
+        assert_eq!(user.union(&computed).get(0, 2), computed.get(0, 2));
+    }
+
+    #[test]
+    fn test_parse_with_bom() {
+        let expected: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let sudoku: Sudoku = format!("\u{FEFF}{}\n", EASY_PUZZLE).parse().unwrap();
+        assert_eq!(sudoku, expected);
+        let sudoku: Sudoku = format!("  \n{}\n\n", EASY_PUZZLE).parse().unwrap();
+        assert_eq!(sudoku, expected);
+
+        let sdm = format!("\u{FEFF}{}\n", EASY_PUZZLE.replace('\n', ""));
+        assert_eq!(Sudoku::read_sdm(sdm.as_bytes()).unwrap(), vec![expected.clone()]);
+        let simple = format!("\u{FEFF}{}", expected.to_simple_sudoku());
+        assert_eq!(Sudoku::from_simple_sudoku(&simple).unwrap(), expected);
+        let jigsaw = format!("\u{FEFF}{}\n\n{}\n", JIGSAW_PUZZLE, JIGSAW_REGIONS);
+        assert!(jigsaw.parse::<JigsawSudoku>().is_ok());
+    }
+
+    #[test]
+    fn test_random_minimal() {
+        let sudoku = Sudoku::random_minimal(3);
+        assert!(sudoku.has_unique_solution());
+        assert!(sudoku.is_minimal());
+        assert!(sudoku.clue_count() < 30);
+        let easy: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert!(!easy.is_minimal());
+    }
+
+    #[test]
+    fn test_write_to() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let mut buf: Vec<u8> = Vec::new();
+        sudoku.write_to(&mut buf).unwrap();
+        assert_eq!(buf, sudoku.to_string().into_bytes());
+        assert!(buf.starts_with(b"5 3 0 | 0 7 0 | 0 0 0 \n"));
+    }
+
+    #[test]
+    fn test_count_conflicts() {
+        let mut sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert_eq!(sudoku.count_conflicts(), 0);
+        sudoku.solve();
+        assert_eq!(sudoku.count_conflicts(), 0);
+        // nine 5s in a row: 36 pairs in the row, 3 in each of the three blocks
+        let mut table = [[0; 9]; 9];
+        table[0] = [5; 9];
+        assert_eq!(Sudoku::from_table(table).count_conflicts(), 45);
+    }
+
+    #[test]
+    fn test_solve_annealing() {
+        let puzzle = Sudoku::generate(Difficulty::Medium, 7).puzzle;
+        let mut expected = puzzle.clone();
+        expected.solve();
+        let solved = (0..5).any(|seed| {
+            let mut sudoku = puzzle.clone();
+            sudoku.solve_annealing(seed) && sudoku == expected
+        });
+        assert!(solved);
+    }
+
+    #[test]
+    fn test_load_from_path() {
+        let dir = std::env::temp_dir();
+        let single = dir.join(format!("sudoku-solver-{}-single.txt", std::process::id()));
+        let many = dir.join(format!("sudoku-solver-{}-many.sdm", std::process::id()));
+        let crlf = dir.join(format!("sudoku-solver-{}-crlf.txt", std::process::id()));
+        std::fs::write(&single, EASY_PUZZLE).unwrap();
+        std::fs::write(&crlf, EASY_PUZZLE.replace('\n', "\r\n") + "\r\n").unwrap();
+        let expected: Sudoku = EASY_PUZZLE.parse().unwrap();
+        Sudoku::write_sdm(&[expected.clone(), expected.clone()], File::create(&many).unwrap()).unwrap();
+        let loaded = Sudoku::load_from_path(&single);
+        let loaded_crlf = Sudoku::load_from_path(&crlf);
+        let collection = Sudoku::load_many_from_path(&many);
+        std::fs::remove_file(&single).unwrap();
+        std::fs::remove_file(&many).unwrap();
+        std::fs::remove_file(&crlf).unwrap();
+        assert_eq!(loaded.unwrap(), expected);
+        assert_eq!(loaded_crlf.unwrap(), expected);
+        assert_eq!(collection.unwrap(), vec![expected.clone(), expected]);
+        assert!(Sudoku::load_from_path(&single).is_err());
+    }
+
+    #[test]
+    fn test_empty_peers() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let peers: Vec<(usize, usize)> = sudoku.empty_peers(4, 4).collect();
+        let expected: Vec<(usize, usize)> = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .filter(|&cell| Sudoku::is_peer((4, 4), cell) && sudoku.get(cell.0, cell.1) == 0)
+            .collect();
+        assert_eq!(peers.len(), expected.len());
+        assert!(peers.iter().all(|cell| expected.contains(cell)));
+        assert_eq!(Sudoku::default().empty_peers(0, 0).count(), 20);
+    }
+
+    #[test]
+    fn test_explain() {
+        let easy: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let narration = easy.explain();
+        assert!(narration.starts_with("1. R5C5 = 5 (naked single); 2. "));
+        assert!(!narration.contains("guessing"));
+        let hard: Sudoku = HARD_PUZZLE.parse().unwrap();
+        assert_eq!(hard.explain(), "1. R8C3 = 1 (hidden single in column 3); 2. no technique applies, guessing is required");
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let shuffled = sudoku.shuffle(11);
+        assert!(shuffled.is_isomorphic_to(&sudoku));
+        assert_ne!(shuffled, sudoku);
+        assert!(shuffled.has_unique_solution());
+        assert_eq!(shuffled.clue_count(), sudoku.clue_count());
+    }
+
+    #[test]
+    fn test_candidate_histogram() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let histogram = sudoku.candidate_histogram();
+        assert_eq!(sudoku.empty_count(), 51);
+        assert_eq!(histogram.iter().sum::<usize>(), sudoku.empty_count());
+        assert_eq!(histogram[0], 0);
+        assert_eq!(Sudoku::default().candidate_histogram(), [0, 0, 0, 0, 0, 0, 0, 0, 0, 81]);
+        assert_eq!(dead_cell_grid().candidate_histogram()[0], 1);
+    }
+
+    #[test]
+    fn test_validate_report() {
+        let mut table = [[0; 9]; 9];
+        table[0][0] = 5;
+        table[0][8] = 5;
+        table[3][3] = 7;
+        table[4][4] = 7;
+        let report = Sudoku::from_table(table).validate_report();
+        assert_eq!(
+            report.violations,
+            vec![
+                Violation { unit: Unit::Row(0), digit: 5, cells: vec![(0, 0), (0, 8)] },
+                Violation { unit: Unit::Block(4), digit: 7, cells: vec![(3, 3), (4, 4)] },
+            ]
+        );
+        assert!(!report.complete);
+        assert!(!report.solved);
+        let mut solved: Sudoku = EASY_PUZZLE.parse().unwrap();
+        solved.solve();
+        assert_eq!(solved.validate_report(), ValidationReport { violations: vec![], complete: true, solved: true });
+    }
+
+    #[test]
+    fn test_from_annotated() {
+        let input = "Puzzle: Wikipedia example\n\
+            # from the Sudoku article\n\
+            53..7....\n6..195...\n.98....6.\n8...6...3\n4..8.3..1\n7...2...6\n.6....28.\n...419..5\n....8..79\n";
+        let (title, sudoku) = Sudoku::from_annotated(input).unwrap();
+        assert_eq!(title.as_deref(), Some("Wikipedia example"));
+        assert_eq!(sudoku, EASY_PUZZLE.parse().unwrap());
+        let (title, _) = Sudoku::from_annotated(EASY_PUZZLE).unwrap();
+        assert_eq!(title, None);
+        assert!(Sudoku::from_annotated("Title\nSecond title\n").is_err());
+    }
+
+    #[test]
+    fn test_difficulty_heatmap() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let heatmap = sudoku.difficulty_heatmap();
+        assert_eq!(heatmap[0][0], 0);
+        // R5C5 is the first cell placed
+        let scores: Vec<u8> = heatmap.iter().flatten().copied().filter(|&score| score != 0).collect();
+        assert_eq!(scores.len(), sudoku.empty_count());
+        assert_eq!(scores.iter().min(), Some(&heatmap[4][4]));
+        assert_eq!(scores.iter().max(), Some(&u8::MAX));
+        assert!(heatmap[4][4] < u8::MAX);
+    }
+
+    #[test]
+    fn test_set_many_rolls_back() {
+        let mut sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let before = sudoku.clone();
+        // the third move puts a second 5 into row 0
+        let err = sudoku.set_many(&[(4, 4, 5), (0, 2, 4), (0, 3, 5), (8, 0, 3)]).unwrap_err();
+        assert_eq!(err.details, "Move 3: Digit 5 appears twice in row 1 at 0,3.");
+        assert_eq!(sudoku, before);
+        assert!(!sudoku.undo());
+        assert!(sudoku.set_many(&[(4, 4, 5), (0, 2, 4)]).is_ok());
+        assert_eq!((sudoku.get(4, 4), sudoku.get(0, 2)), (5, 4));
+        assert!(sudoku.set_many(&[(0, 3, 6), (9, 0, 1)]).is_err());
+        assert_eq!(sudoku.get(0, 3), 0);
+    }
+
+    #[test]
+    fn test_solve_with_max_depth() {
+        let mut empty = Sudoku::default();
+        assert_eq!(empty.solve_with_max_depth(2), SolveOutcome::DepthExceeded);
+        assert_eq!(empty, Sudoku::default());
+        assert_eq!(empty.solve_with_max_depth(81), SolveOutcome::Solved);
+        assert!(empty.is_solved());
+        let mut easy: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert_eq!(easy.solve_with_max_depth(0), SolveOutcome::Solved);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let bytes = sudoku.as_bytes();
+        assert_eq!(&bytes[..9], &[5, 3, 0, 0, 7, 0, 0, 0, 0]);
+        let decoded = Sudoku::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, sudoku);
+        assert!(decoded.is_given(0, 0) && !decoded.is_given(0, 2));
+        let mut bytes = bytes;
+        bytes[10] = 10;
+        assert_eq!(Sudoku::from_bytes(&bytes).unwrap_err().details, "Invalid value 10 at 1,1.");
+    }
+
+    #[test]
+    fn test_diagonals() {
+        let [main, anti] = Sudoku::diagonals();
+        assert_eq!((main[0], main[8]), ((0, 0), (8, 8)));
+        assert_eq!((anti[0], anti[8]), ((0, 8), (8, 0)));
+        assert!(main.iter().chain(&anti).all(|&(row, col)| row == col || row + col == 8));
+        let mut table = [[0; 9]; 9];
+        table[0][0] = 1;
+        table[4][4] = 1;
+        table[2][6] = 3;
+        let sudoku = Sudoku::from_table(table);
+        assert!(sudoku.consistent());
+        assert!(!sudoku.diagonal_is_valid(0));
+        assert!(sudoku.diagonal_is_valid(1));
+    }
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(Sudoku::default().progress(), 0.0);
+        let mut sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        assert_eq!(sudoku.progress(), 30.0 / 81.0);
+        sudoku.solve();
+        assert_eq!(sudoku.progress(), 1.0);
+    }
+
+    #[test]
+    fn test_assume() {
+        let sudoku: Sudoku = EASY_PUZZLE.parse().unwrap();
+        // R1C3 can only hold 1, 2 or 4 and the solution has a 4 there
+        let branch = sudoku.assume(0, 2, 4).unwrap();
+        assert!(branch.is_solved());
+        assert_eq!(sudoku.get(0, 2), 0);
+        assert_eq!(sudoku.assume(0, 2, 1).unwrap_err().details, "Assuming 1 at 0,2 leads to a contradiction.");
+        assert!(sudoku.assume(0, 2, 5).is_err());
+        assert!(sudoku.assume(0, 0, 5).is_err());
+        let hard: Sudoku = HARD_PUZZLE.parse().unwrap();
+        assert!(hard.assume(1, 0, 4).unwrap().clue_count() > hard.clue_count() + 1);
+    }
+
+    #[test]
+    fn test_sort_by_difficulty() {
+        let easy: Sudoku = EASY_PUZZLE.parse().unwrap();
+        let hard: Sudoku = HARD_PUZZLE.parse().unwrap();
+        let middle: Sudoku = "300500100000840000002001605185030000000005030090000000400000007030786200000000008"
+            .parse()
+            .unwrap();
+        let mut puzzles = vec![hard.clone(), easy.clone(), middle.clone()];
+        puzzles.sort_by_key(|sudoku| sudoku.rate());
+        assert_eq!(puzzles, vec![easy.clone(), middle.clone(), hard.clone()]);
+        assert_eq!(puzzles.iter().map(Sudoku::rate).collect::<Vec<_>>(), [Difficulty::Easy, Difficulty::Hard, Difficulty::Expert]);
+        assert!(hard.harder_than(&middle) && middle.harder_than(&easy));
+        assert!(!easy.harder_than(&easy));
+    }
+
+    /// Puzzles with a unique solution, from logic-only to guess-heavy and generated ones
+    fn sample_puzzles() -> Vec<Sudoku> {
+        vec![
+            EASY_PUZZLE.parse().unwrap(),
+            HARD_PUZZLE.parse().unwrap(),
+            "300500100000840000002001605185030000000005030090000000400000007030786200000000008".parse().unwrap(),
+            Sudoku::generate_with_clues(28, 1).unwrap(),
+            Sudoku::random_minimal(3),
+        ]
+    }
+
+    #[test]
+    fn test_solvers_agree() {
+        for puzzle in sample_puzzles() {
+            assert!(puzzle.has_unique_solution(), "{}", puzzle);
+            let mut backtracked = puzzle.clone();
+            let mut propagated = puzzle.clone();
+            assert!(backtracked.solve_with(Strategy::Backtrack), "{}", puzzle);
+            assert!(propagated.solve_with(Strategy::ConstraintPropagation), "{}", puzzle);
+            assert!(backtracked.is_solved());
+            assert_eq!(backtracked, propagated, "{}", puzzle);
+            assert_eq!(Some(backtracked), puzzle.solution(), "{}", puzzle);
+        }
+    }
+
+    #[test]
+    fn test_explain_contradiction() {
+        let sudoku = dead_cell_grid();
+        assert!(sudoku.consistent());
+        assert_eq!(sudoku.explain(), "1. an empty cell has no candidates left, the puzzle is contradictory");
+    }
+
+    #[test]
+    fn test_guess_hint_uses_solution() {
+        // AI Escargot with 4 and 6 swapped: once R8C3 is placed no technique applies and the
+        // most constrained cell R2C3 has candidates 4 and 6, of which the solution holds 6
+        let swapped: String = HARD_PUZZLE
+            .chars()
+            .map(|c| match c {
+                '4' => '6',
+                '6' => '4',
+                c => c,
+            })
+            .collect();
+        let mut sudoku: Sudoku = swapped.parse().unwrap();
+        sudoku.set(7, 2, 1).unwrap();
+        let hint = sudoku.next_hint().unwrap();
+        assert_eq!(hint.technique, None);
+        assert_eq!((hint.row, hint.col), (1, 2));
+        assert_eq!(sudoku.candidates(1, 2), vec![4, 6]);
+        assert_eq!(hint.value, 6);
+        assert_eq!(sudoku.solution().unwrap().get(1, 2), 6);
     }
 }